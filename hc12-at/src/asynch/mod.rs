@@ -0,0 +1,548 @@
+//! Async type-state driven state machine of Hc12.
+//!
+//! Mirrors [`crate::hc12::Hc12`], but awaits the serial port and the delay instead of blocking.
+//! Timeouts race the response against the delay.
+//! Checking responses and keeping track of Hc12 is shared with the blocking driver.
+
+use core::future::Future;
+use core::marker::PhantomData;
+
+use embassy_futures::select::{select, Either};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_io_async::{ErrorType, Read, Write};
+
+use crate::hc12::state::{
+    check_response, confirm_again, parse_parameters, DriverState, ParameterValue,
+};
+use crate::hc12::timing::Timing;
+use crate::hc12::{Configuration, Normal, Sleep, TransitionError};
+use crate::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, parameters::Parameters,
+    serial_format::SerialFormat, transmission_power::TransmissionPower, OK_QUERY, OK_RESPONSE,
+    QUERY_PARAMS_COMMAND, RESET_SETTINGS_COMMAND, RESET_SETTINGS_RESPONSE, SLEEP_COMMAND,
+    SLEEP_RESPONSE, VERSION_QUERY,
+};
+use crate::settings::query::MakeQuery;
+use crate::settings::version::{Capabilities, FirmwareVersion};
+
+#[cfg(test)]
+mod test;
+
+/// Time in microseconds without input after which stale input is considered discarded
+const DISCARD_IDLE_US: u32 = 1000;
+
+/// Error of the serial port `S`
+type SerialError<S> = <S as ErrorType>::Error;
+
+/// Error of the SET pin `P`
+type PinError<P> = <P as embedded_hal::digital::ErrorType>::Error;
+
+/// Error of an async Hc12 with serial port `S` and SET pin `P`
+pub type Hc12Error<S, P> = crate::Error<SerialError<S>, PinError<P>>;
+
+/// Fallible transition of an async Hc12 with serial port `S` and SET pin `P`
+type Transition<From, To, S, P> =
+    core::result::Result<To, TransitionError<From, SerialError<S>, PinError<P>>>;
+
+/// Async Hc12 resources: A serial port, an output pin, and a Delay.
+#[derive(Debug)]
+pub struct Hc12<S, P, D, M>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    serial: S,
+    set_pin: P,
+    delay: D,
+    state: DriverState,
+    mode: PhantomData<M>,
+}
+
+/// Read into buffer until a newline was received or the buffer is full.
+/// `count` holds the number of bytes read, even if the future is dropped early.
+async fn read_line<S: Read>(
+    serial: &mut S,
+    buffer: &mut [u8],
+    count: &mut usize,
+) -> Result<(), S::Error> {
+    while *count < buffer.len() {
+        let mut byte = [0u8];
+        if serial.read(&mut byte).await? == 0 {
+            continue;
+        }
+        buffer[*count] = byte[0];
+        *count += 1;
+        if byte[0] == b'\n' {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Implementation for all modes of async Hc12
+impl<S, P, D, M> Hc12<S, P, D, M>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Time in milliseconds Hc12 has to respond to a command
+    pub fn timeout_ms(&self) -> u16 {
+        self.state.timeout_ms
+    }
+
+    /// Set the time in milliseconds Hc12 has to respond to a command
+    pub fn set_timeout_ms(&mut self, timeout_ms: u16) {
+        self.state.timeout_ms = timeout_ms;
+    }
+
+    /// Waits around mode transitions
+    pub fn timing(&self) -> &Timing {
+        &self.state.timing
+    }
+
+    /// Set the waits around mode transitions, for modules which need longer than the datasheet says
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.state.timing = timing;
+    }
+
+    /// Features of the firmware, as far as they are known.
+    /// Until the firmware version was read, all features are assumed.
+    pub fn capabilities(&self) -> &Capabilities {
+        self.state.capabilities()
+    }
+
+    /// Set the features of the firmware, for firmware whose version is not recognised, like that of clones.
+    /// Reading the firmware version replaces them again.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.state.set_capabilities(capabilities);
+    }
+
+    /// Parameters as last read from or set on Hc12, the defaults until then
+    pub fn known_parameters(&self) -> &Parameters {
+        &self.state.parameters
+    }
+
+    /// Tell the driver the parameters of Hc12, if they are known without reading them
+    pub fn set_known_parameters(&mut self, parameters: Parameters) {
+        self.state.parameters = parameters;
+    }
+
+    /// Change the mode marker, keeping all resources
+    fn into_mode<N>(self) -> Hc12<S, P, D, N> {
+        Hc12 {
+            serial: self.serial,
+            set_pin: self.set_pin,
+            delay: self.delay,
+            state: self.state,
+            mode: PhantomData::<N>,
+        }
+    }
+
+    /// Pull SET low and confirm that Hc12 accepts commands.
+    ///
+    /// After the wait for config mode, stale input like received radio data is discarded
+    /// and Hc12 is queried with "AT" until it answers "OK".
+    /// When it never does, SET is released again and Hc12 is given `Timing::exit_configuration_ms` to settle.
+    async fn enter_configuration(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.set_pin.set_low().map_err(crate::Error::Pin)?;
+        self.delay
+            .delay_ms(self.state.timing.enter_configuration_ms)
+            .await;
+        if self.state.timing.confirm_attempts == 0 {
+            return Ok(());
+        }
+        let mut result = Err(crate::Error::NoResponse);
+        for attempt in 0..self.state.timing.confirm_attempts {
+            if attempt > 0 {
+                self.delay
+                    .delay_ms(self.state.timing.confirm_interval_ms)
+                    .await;
+            }
+            result = match self.discard_input().await {
+                Ok(()) => self.expect_response(&OK_QUERY, &OK_RESPONSE).await,
+                Err(e) => Err(e),
+            };
+            if !confirm_again(&result) {
+                break;
+            }
+        }
+        if result.is_err() {
+            let _ = self.set_pin.set_high();
+            self.delay
+                .delay_ms(self.state.timing.exit_configuration_ms)
+                .await;
+        }
+        result
+    }
+
+    /// Read and drop all bytes which arrive before the serial port is idle for `DISCARD_IDLE_US`
+    async fn discard_input(&mut self) -> Result<(), Hc12Error<S, P>> {
+        let mut byte = [0u8];
+        loop {
+            match select(
+                self.serial.read(&mut byte),
+                self.delay.delay_us(DISCARD_IDLE_US),
+            )
+            .await
+            {
+                Either::First(result) => result.map_err(crate::Error::Read)?,
+                Either::Second(()) => return Ok(()),
+            };
+        }
+    }
+
+    /// Wrap this Hc12 with the reason why it could not change its mode
+    fn transition_error(
+        self,
+        error: Hc12Error<S, P>,
+    ) -> TransitionError<Self, SerialError<S>, PinError<P>> {
+        TransitionError { hc12: self, error }
+    }
+
+    /// Write all bytes to serial port
+    async fn write_all(&mut self, bytes: &[u8]) -> Result<(), Hc12Error<S, P>> {
+        self.serial
+            .write_all(bytes)
+            .await
+            .map_err(crate::Error::Write)
+    }
+
+    /// Read lines into the buffers, all within the timeout.
+    /// Returns the number of bytes read into each buffer.
+    async fn read_lines<const N: usize>(
+        &mut self,
+        buffers: &mut [[u8; 16]; N],
+    ) -> Result<[usize; N], Hc12Error<S, P>> {
+        let mut counts = [0usize; N];
+        let serial = &mut self.serial;
+        let counts_ref = &mut counts;
+        let lines = async move {
+            for (buffer, count) in buffers.iter_mut().zip(counts_ref.iter_mut()) {
+                read_line(serial, buffer, count).await?;
+            }
+            Ok(())
+        };
+        match select(lines, self.delay.delay_ms(u32::from(self.state.timeout_ms))).await {
+            Either::First(result) => result.map_err(crate::Error::Read)?,
+            Either::Second(()) if counts[0] == 0 => return Err(crate::Error::NoResponse),
+            Either::Second(()) => return Err(crate::Error::Timeout),
+        }
+        Ok(counts)
+    }
+
+    /// Send a command and check that Hc12 answers with exactly the expected response.
+    async fn expect_response(
+        &mut self,
+        command: &[u8],
+        expected: &[u8],
+    ) -> Result<(), Hc12Error<S, P>> {
+        self.write_all(command).await?;
+        let mut buffer = [[0u8; 16]];
+        let [count] = self.read_lines(&mut buffer).await?;
+        check_response(&buffer[0][..count], expected)
+    }
+}
+
+/// Implementation for normal mode of async Hc12
+impl<S, P, D> Hc12<S, P, D, Normal>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Construct an async Hc12 in normal mode
+    pub async fn new(serial: S, mut set_pin: P, mut delay: D) -> Result<Self, Hc12Error<S, P>> {
+        let timing = Timing::default();
+        set_pin.set_high().map_err(crate::Error::Pin)?;
+        delay.delay_ms(timing.exit_configuration_ms).await;
+        Ok(Self {
+            serial,
+            set_pin,
+            delay,
+            state: DriverState::new(timing),
+            mode: PhantomData::<Normal>,
+        })
+    }
+
+    /// Release the contained resources
+    pub fn release(self) -> (S, P, D) {
+        (self.serial, self.set_pin, self.delay)
+    }
+
+    /// Write entire buffer to serial port.
+    ///
+    /// In modes which limit the packet length, like FU4, the buffer is one packet:
+    /// a longer buffer fails with `PacketTooLong` and nothing is written.
+    pub async fn write_buffer(&mut self, buffer: &[u8]) -> Result<(), Hc12Error<S, P>> {
+        self.state.check_packet(buffer.len())?;
+        self.write_all(buffer).await?;
+        self.state.written(buffer.len());
+        Ok(())
+    }
+
+    /// Read from serial port until the buffer is full or `timeout_ms` passed.
+    /// Returns the number of bytes actually received.
+    pub async fn read_buffer_timeout(
+        &mut self,
+        buffer: &mut [u8],
+        timeout_ms: u16,
+    ) -> Result<usize, Hc12Error<S, P>> {
+        let mut count = 0;
+        let serial = &mut self.serial;
+        let count_ref = &mut count;
+        let fill = async move {
+            while *count_ref < buffer.len() {
+                *count_ref += serial.read(&mut buffer[*count_ref..]).await?;
+            }
+            Ok(())
+        };
+        if let Either::First(result) = select(fill, with_delay(&mut self.delay, timeout_ms)).await {
+            result.map_err(crate::Error::Read)?;
+        }
+        Ok(count)
+    }
+
+    /// Move Hc12 in normal mode to Hc12 in config mode.
+    ///
+    /// Bytes written before are flushed and given time to go over the air, see `Parameters::transmission_time_ms`.
+    /// Only the last `MAX_PENDING_LEN` bytes are waited for, as the module sent the ones before while they were written.
+    /// Config mode is confirmed with "AT", see `Timing::confirm_attempts`.
+    /// If Hc12 does not answer, SET is released and Hc12 is returned in normal mode with the error.
+    pub async fn into_configuration_mode(
+        mut self,
+    ) -> Transition<Self, Hc12<S, P, D, Configuration>, S, P> {
+        if let Err(e) = self.drain().await {
+            return Err(self.transition_error(e));
+        }
+        match self.enter_configuration().await {
+            Ok(()) => Ok(self.into_mode()),
+            Err(e) => Err(self.transition_error(e)),
+        }
+    }
+
+    /// Flush the serial port and wait until Hc12 sent the bytes written since the last transition
+    async fn drain(&mut self) -> Result<(), Hc12Error<S, P>> {
+        let Some(time_ms) = self.state.drain_time_ms() else {
+            return Ok(());
+        };
+        self.serial.flush().await.map_err(crate::Error::Write)?;
+        self.delay.delay_ms(time_ms).await;
+        self.state.drained();
+        Ok(())
+    }
+}
+
+/// Wait for `timeout_ms` milliseconds
+fn with_delay<D: DelayNs>(delay: &mut D, timeout_ms: u16) -> impl Future<Output = ()> + '_ {
+    delay.delay_ms(u32::from(timeout_ms))
+}
+
+/// Async Hc12 in normal mode reports the errors of the underlying serial port.
+impl<S, P, D> ErrorType for Hc12<S, P, D, Normal>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    type Error = SerialError<S>;
+}
+
+/// Implement async Read for Hc12 in normal mode
+/// This just defers to the underlying serial implementation.
+impl<S, P, D> Read for Hc12<S, P, D, Normal>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.serial.read(buf).await
+    }
+}
+
+/// Implement async Write for Hc12 in normal mode.
+/// This just defers to the underlying serial implementation.
+impl<S, P, D> Write for Hc12<S, P, D, Normal>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let count = self.serial.write(buf).await?;
+        self.state.written(count);
+        Ok(count)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.serial.flush().await
+    }
+}
+
+/// Implementation for async Hc12 in config mode.
+impl<S, P, D> Hc12<S, P, D, Configuration>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Move Hc12 in config mode back to normal mode.
+    pub async fn into_normal_mode(mut self) -> Transition<Self, Hc12<S, P, D, Normal>, S, P> {
+        if let Err(e) = self.set_pin.set_high() {
+            return Err(self.transition_error(crate::Error::Pin(e)));
+        }
+        self.delay
+            .delay_ms(self.state.timing.exit_configuration_ms)
+            .await;
+        Ok(self.into_mode())
+    }
+
+    /// Move Hc12 in config mode into sleeping mode.
+    pub async fn into_sleeping_mode(mut self) -> Transition<Self, Hc12<S, P, D, Sleep>, S, P> {
+        if let Err(e) = self.expect_response(&SLEEP_COMMAND, &SLEEP_RESPONSE).await {
+            return Err(self.transition_error(e));
+        }
+        if let Err(e) = self.set_pin.set_high() {
+            return Err(self.transition_error(crate::Error::Pin(e)));
+        }
+        self.delay
+            .delay_ms(self.state.timing.exit_configuration_ms)
+            .await;
+        Ok(self.into_mode())
+    }
+
+    /// Check if Hc12 responds to "AT" query with "OK".
+    pub async fn is_ok(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.expect_response(&OK_QUERY, &OK_RESPONSE).await
+    }
+
+    /// Get version as a string from Hc12
+    pub async fn get_version<'a>(
+        &mut self,
+        buffer: &'a mut [u8; 64],
+    ) -> Result<&'a [u8], Hc12Error<S, P>> {
+        self.write_all(&VERSION_QUERY).await?;
+        let mut count = 0;
+        let line = read_line(&mut self.serial, buffer, &mut count);
+        match select(line, with_delay(&mut self.delay, self.state.timeout_ms)).await {
+            Either::First(result) => result.map_err(crate::Error::Read)?,
+            Either::Second(()) if count == 0 => return Err(crate::Error::NoResponse),
+            Either::Second(()) => return Err(crate::Error::Timeout),
+        }
+        Ok(&buffer[..count])
+    }
+
+    /// Get the firmware version of Hc12.
+    ///
+    /// Afterwards, commands the firmware does not implement fail with `Unsupported`,
+    /// and `get_parameters` also queries the serial format if the firmware implements "AT+U".
+    pub async fn get_firmware_version(&mut self) -> Result<FirmwareVersion, Hc12Error<S, P>> {
+        let mut buffer = [0u8; 64];
+        let line = self.get_version(&mut buffer).await?;
+        self.state.record_version(line)
+    }
+
+    /// Reset Hc12 to default settings.
+    pub async fn reset_settings(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.expect_response(&RESET_SETTINGS_COMMAND, &RESET_SETTINGS_RESPONSE)
+            .await?;
+        self.state.record_reset();
+        Ok(())
+    }
+
+    /// Get parameters of Hc12.
+    ///
+    /// Until the firmware version was read, the serial format is not queried, and the one last set is reported.
+    pub async fn get_parameters(&mut self) -> Result<Parameters, Hc12Error<S, P>> {
+        self.write_all(&QUERY_PARAMS_COMMAND).await?;
+        let mut params = [[0u8; 16]; 4];
+        let counts = self.read_lines(&mut params).await?;
+        let parameters = parse_parameters([
+            &params[0][..counts[0]],
+            &params[1][..counts[1]],
+            &params[2][..counts[2]],
+            &params[3][..counts[3]],
+        ])?;
+        let mut format = [[0u8; 16]];
+        let response = if self.state.queries_serial_format() {
+            let mut query = [0u8; 7];
+            SerialFormat::make_query(&mut query);
+            self.write_all(&query).await?;
+            Some(
+                self.read_lines(&mut format)
+                    .await
+                    .map(|[count]| &format[0][..count]),
+            )
+        } else {
+            None
+        };
+        self.state.record_parameters(parameters, response)
+    }
+
+    /// Set the baud rate of Hc12 and verify the acknowledgement.
+    ///
+    /// Only Hc12 is changed: the serial port of the host keeps its baud rate and has to follow.
+    /// The new baud rate takes effect at the latest when Hc12 leaves config mode, and right away with some firmware.
+    /// After a power-up with SET low, it only takes effect when Hc12 leaves config mode.
+    /// Unlike the blocking driver, with `change_baud_rate` and `apply`, this driver cannot switch the serial port of the host along.
+    pub async fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&rate).await
+    }
+
+    /// Set the channel of Hc12 and verify the acknowledgement.
+    pub async fn set_channel(&mut self, channel: Channel) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&channel).await
+    }
+
+    /// Set the operation mode of Hc12 and verify the acknowledgement.
+    pub async fn set_mode(&mut self, mode: Mode) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&mode).await
+    }
+
+    /// Set the transmission power of Hc12 and verify the acknowledgement.
+    pub async fn set_power(&mut self, power: TransmissionPower) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&power).await
+    }
+
+    /// Set the serial data format of Hc12 and verify the acknowledgement.
+    ///
+    /// Hc12 switches its serial port to the new format right away, so the host has to follow.
+    pub async fn set_serial_format(&mut self, format: SerialFormat) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&format).await
+    }
+
+    /// Send the command for a parameter, then check that Hc12 acknowledged the same value.
+    /// Fails with `Unsupported` before sending if the firmware does not implement the value.
+    async fn set_parameter<T: ParameterValue>(&mut self, value: &T) -> Result<(), Hc12Error<S, P>> {
+        self.state.check_supported(value)?;
+        let mut command = [0u8; 16];
+        let command = value.make_command(&mut command);
+        self.write_all(command).await?;
+        let mut response = [[0u8; 16]];
+        let [count] = self.read_lines(&mut response).await?;
+        self.state.record_setting(value, &response[0][..count])
+    }
+}
+
+/// Implementation for async Hc12 in sleeping mode.
+impl<S, P, D> Hc12<S, P, D, Sleep>
+where
+    S: Read + Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Move Hc12 in sleeping mode back to configuration mode.
+    ///
+    /// Config mode is confirmed like when coming from normal mode.
+    pub async fn into_configuration_mode(
+        mut self,
+    ) -> Transition<Self, Hc12<S, P, D, Configuration>, S, P> {
+        match self.enter_configuration().await {
+            Ok(()) => Ok(self.into_mode()),
+            Err(e) => Err(self.transition_error(e)),
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use crate::hc12::timing::Timing;
+use crate::settings::parameter::serial_format::SerialFormat;
+use crate::settings::parameter::transmission_power::TransmissionPower;
+
+use super::*;
+
+use debugless_unwrap::{DebuglessUnwrap, DebuglessUnwrapErr};
+use embassy_futures::block_on;
+use embedded_hal_mock::eh1::digital::State;
+use embedded_hal_mock::eh1::{delay::NoopDelay as MockNoop, digital as pin, serial};
+use embedded_hal_nb::serial::{ErrorKind, Read as _};
+
+/// Error of the serial mock
+#[derive(Debug)]
+struct SerialError(ErrorKind);
+
+impl embedded_io_async::Error for SerialError {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        embedded_io_async::ErrorKind::Other
+    }
+}
+
+/// embedded-io-async view of the serial mock, which is based on embedded-hal-nb.
+/// A `WouldBlock` read stays pending until the driver gives up waiting.
+struct Serial {
+    mock: serial::Mock<u8>,
+}
+
+impl Serial {
+    fn new(transactions: &[serial::Transaction<u8>]) -> Self {
+        Self {
+            mock: serial::Mock::new(transactions),
+        }
+    }
+
+    fn done(&mut self) {
+        self.mock.done();
+    }
+}
+
+impl embedded_io_async::ErrorType for Serial {
+    type Error = SerialError;
+}
+
+impl embedded_io_async::Read for Serial {
+    async fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        loop {
+            match self.mock.read() {
+                Ok(byte) => {
+                    buf[0] = byte;
+                    return Ok(1);
+                }
+                Err(nb::Error::WouldBlock) => core::future::pending::<()>().await,
+                Err(nb::Error::Other(e)) => return Err(SerialError(e)),
+            }
+        }
+    }
+}
+
+impl embedded_io_async::Write for Serial {
+    async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        for byte in buf {
+            nb::block!(embedded_hal_nb::serial::Write::write(&mut self.mock, *byte))
+                .map_err(SerialError)?;
+        }
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        nb::block!(embedded_hal_nb::serial::Write::flush(&mut self.mock)).map_err(SerialError)
+    }
+}
+
+#[test]
+fn is_ok() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        hc12.is_ok().await.unwrap();
+        let hc12 = hc12.into_normal_mode().await.debugless_unwrap();
+        let (mut serial, mut set_pin, _) = hc12.release();
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn send_and_receive_buffer() {
+    let delay = MockNoop;
+    let pin_transactions = [pin::Transaction::set(State::High)];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::write_many(b"data"),
+        serial::Transaction::read_many(b"reply"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let mut hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        hc12.write_buffer(b"data").await.unwrap();
+        let mut buffer = [0u8; 8];
+        let count = hc12.read_buffer_timeout(&mut buffer, 10).await.unwrap();
+        assert_eq!(&buffer[..count], b"reply");
+        let (mut serial, mut set_pin, _) = hc12.release();
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn get_version() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+V\r\n"),
+        serial::Transaction::read_many(b"HC-12_VFAKE\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let mut buffer = [0u8; 64];
+        let result = hc12.get_version(&mut buffer).await.unwrap();
+        assert_eq!(result, b"HC-12_VFAKE\r\n");
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn get_parameters() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let params = block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let params = hc12.get_parameters().await.unwrap();
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+        params
+    });
+    let expected = Parameters {
+        baud_rate: BaudRate::Bps9600,
+        channel: Channel::new(1).unwrap(),
+        power: TransmissionPower::Eight,
+        mode: Mode::Fu3,
+        serial_format: SerialFormat::default(),
+    };
+    assert_eq!(expected, params);
+}
+
+#[test]
+fn set_parameters() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+B19200\r\n"),
+        serial::Transaction::read_many(b"OK+B19200\r\n"),
+        serial::Transaction::write_many(b"AT+C021\r\n"),
+        serial::Transaction::read_many(b"OK+C021\r\n"),
+        serial::Transaction::write_many(b"AT+FU1\r\n"),
+        serial::Transaction::read_many(b"OK+FU2\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        hc12.set_baud_rate(BaudRate::Bps19200).await.unwrap();
+        hc12.set_channel(Channel::new(21).unwrap()).await.unwrap();
+        let err = hc12.set_mode(Mode::Fu1).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Mismatch));
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn go_to_sleep_and_wake_up() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+SLEEP\r\n"),
+        serial::Transaction::read_many(b"OK+SLEEP\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let hc12 = hc12.into_sleeping_mode().await.debugless_unwrap();
+        let hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn command_timeout() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC0"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let err = hc12.is_ok().await.unwrap_err();
+        assert!(matches!(err, crate::Error::NoResponse));
+        let err = hc12.get_parameters().await.unwrap_err();
+        assert!(matches!(err, crate::Error::Timeout));
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn failed_sleep_returns_driver_and_cause() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+SLEEP\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let failed = hc12.into_sleeping_mode().await.debugless_unwrap_err();
+        assert!(matches!(failed.error, crate::Error::UnexpectedResponse(_)));
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = failed.hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn serial_error() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_error(nb::Error::Other(ErrorKind::Overrun)),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        let mut hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let err = hc12.is_ok().await.unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Read(SerialError(ErrorKind::Overrun))
+        ));
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn confirm_configuration_mode() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let mut transactions = vec![
+        // Received radio data is discarded
+        serial::Transaction::read_many(b"data"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        // Not ready yet
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    for _ in 0..2 {
+        transactions.push(serial::Transaction::read_error(nb::Error::WouldBlock));
+        transactions.push(serial::Transaction::write_many(b"AT\r\n"));
+        transactions.push(serial::Transaction::read_error(nb::Error::WouldBlock));
+    }
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let mut hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        hc12.set_timing(Timing {
+            confirm_attempts: 2,
+            ..Timing::default()
+        });
+        let hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let hc12 = hc12.into_normal_mode().await.debugless_unwrap();
+        let TransitionError { hc12, error } =
+            hc12.into_configuration_mode().await.debugless_unwrap_err();
+        assert!(matches!(error, crate::Error::NoResponse));
+        let (mut serial, mut set_pin, _) = hc12.release();
+        serial.done();
+        set_pin.done();
+    });
+}
+
+#[test]
+fn drain_before_configuration_mode() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ]);
+    let transactions = [
+        serial::Transaction::write_many(b"some data"),
+        serial::Transaction::flush(),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    block_on(async {
+        let mut hc12 = Hc12::new(serial, set_pin, delay).await.unwrap();
+        hc12.write_buffer(b"some data").await.unwrap();
+        let hc12 = hc12.into_configuration_mode().await.debugless_unwrap();
+        let Hc12 {
+            mut serial,
+            mut set_pin,
+            ..
+        } = hc12;
+        serial.done();
+        set_pin.done();
+    });
+}
@@ -0,0 +1,271 @@
+//! Adapters for serial ports, pins and delays implementing embedded-hal 0.2.
+//!
+//! Wrap the resources of an embedded-hal 0.2 HAL, then use them with the driver as usual:
+//!
+//! ```ignore
+//! let hc12 = hc12_at::compat::new(serial, set_pin, delay)?;
+//! ```
+
+use core::fmt::Debug;
+
+use embedded_hal_02::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal_02::digital::v2::OutputPin as OutputPin02;
+use embedded_hal_02::serial::{Read as Read02, Write as Write02};
+use nb::block;
+
+use crate::hc12::{Hc12Error, Normal};
+
+/// Hc12 driving embedded-hal 0.2 resources
+pub type Hc12<S, P, D, M> = crate::hc12::Hc12<Serial<S>, Pin<P>, Delay<D>, M>;
+
+/// Construct an Hc12 in normal mode from embedded-hal 0.2 resources
+#[allow(clippy::type_complexity)]
+pub fn new<S, P, D>(
+    serial: S,
+    set_pin: P,
+    delay: D,
+) -> Result<Hc12<S, P, D, Normal>, Hc12Error<Serial<S>, Pin<P>>>
+where
+    S: Read02<u8> + Write02<u8>,
+    <S as Read02<u8>>::Error: Debug,
+    <S as Write02<u8>>::Error: Debug,
+    P: OutputPin02,
+    P::Error: Debug,
+    D: DelayMs<u16> + DelayUs<u16>,
+{
+    crate::hc12::Hc12::new(Serial::new(serial), Pin::new(set_pin), Delay::new(delay))
+}
+
+/// Error of an embedded-hal 0.2 serial port
+#[derive(Debug)]
+pub enum SerialError<R, W> {
+    /// Reading failed
+    Read(R),
+    /// Writing or flushing failed
+    Write(W),
+}
+
+impl<R: Debug, W: Debug> embedded_io::Error for SerialError<R, W> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::Other
+    }
+}
+
+/// embedded-io serial port backed by an embedded-hal 0.2 serial port
+#[derive(Debug)]
+pub struct Serial<S> {
+    serial: S,
+    pending: Option<u8>,
+}
+
+impl<S> Serial<S> {
+    /// Wrap an embedded-hal 0.2 serial port
+    pub fn new(serial: S) -> Self {
+        Self {
+            serial,
+            pending: None,
+        }
+    }
+
+    /// Release the wrapped serial port
+    pub fn release(self) -> S {
+        self.serial
+    }
+}
+
+impl<S> embedded_io::ErrorType for Serial<S>
+where
+    S: Read02<u8> + Write02<u8>,
+    <S as Read02<u8>>::Error: Debug,
+    <S as Write02<u8>>::Error: Debug,
+{
+    type Error = SerialError<<S as Read02<u8>>::Error, <S as Write02<u8>>::Error>;
+}
+
+impl<S> embedded_io::Read for Serial<S>
+where
+    S: Read02<u8> + Write02<u8>,
+    <S as Read02<u8>>::Error: Debug,
+    <S as Write02<u8>>::Error: Debug,
+{
+    /// Block until a byte arrived, then read what is available without blocking.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = match self.pending.take() {
+            Some(byte) => byte,
+            None => block!(self.serial.read()).map_err(SerialError::Read)?,
+        };
+        let mut count = 1;
+        for v in &mut buf[1..] {
+            match self.serial.read() {
+                Ok(byte) => *v = byte,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(SerialError::Read(e)),
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<S> embedded_io::ReadReady for Serial<S>
+where
+    S: Read02<u8> + Write02<u8>,
+    <S as Read02<u8>>::Error: Debug,
+    <S as Write02<u8>>::Error: Debug,
+{
+    /// embedded-hal 0.2 cannot peek, so a received byte is kept until the next read.
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        if self.pending.is_none() {
+            match self.serial.read() {
+                Ok(byte) => self.pending = Some(byte),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(e)) => return Err(SerialError::Read(e)),
+            }
+        }
+        Ok(self.pending.is_some())
+    }
+}
+
+impl<S> embedded_io::Write for Serial<S>
+where
+    S: Read02<u8> + Write02<u8>,
+    <S as Read02<u8>>::Error: Debug,
+    <S as Write02<u8>>::Error: Debug,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for byte in buf {
+            block!(self.serial.write(*byte)).map_err(SerialError::Write)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block!(self.serial.flush()).map_err(SerialError::Write)
+    }
+}
+
+/// Error of an embedded-hal 0.2 output pin
+#[derive(Debug)]
+pub struct PinError<E>(pub E);
+
+impl<E: Debug> embedded_hal::digital::Error for PinError<E> {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+
+/// embedded-hal 1.0 output pin backed by an embedded-hal 0.2 output pin
+#[derive(Debug)]
+pub struct Pin<P>(P);
+
+impl<P> Pin<P> {
+    /// Wrap an embedded-hal 0.2 output pin
+    pub fn new(pin: P) -> Self {
+        Self(pin)
+    }
+
+    /// Release the wrapped pin
+    pub fn release(self) -> P {
+        self.0
+    }
+}
+
+impl<P> embedded_hal::digital::ErrorType for Pin<P>
+where
+    P: OutputPin02,
+    P::Error: Debug,
+{
+    type Error = PinError<P::Error>;
+}
+
+impl<P> embedded_hal::digital::OutputPin for Pin<P>
+where
+    P: OutputPin02,
+    P::Error: Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low().map_err(PinError)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high().map_err(PinError)
+    }
+}
+
+/// embedded-hal 1.0 delay backed by an embedded-hal 0.2 delay
+#[derive(Debug)]
+pub struct Delay<D>(D);
+
+impl<D> Delay<D> {
+    /// Wrap an embedded-hal 0.2 delay
+    pub fn new(delay: D) -> Self {
+        Self(delay)
+    }
+
+    /// Release the wrapped delay
+    pub fn release(self) -> D {
+        self.0
+    }
+}
+
+impl<D> embedded_hal::delay::DelayNs for Delay<D>
+where
+    D: DelayMs<u16> + DelayUs<u16>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, mut us: u32) {
+        while us > 0 {
+            let chunk = us.min(u32::from(u16::MAX));
+            self.0.delay_us(chunk as u16);
+            us -= chunk;
+        }
+    }
+
+    fn delay_ms(&mut self, mut ms: u32) {
+        while ms > 0 {
+            let chunk = ms.min(u32::from(u16::MAX));
+            self.0.delay_ms(chunk as u16);
+            ms -= chunk;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use debugless_unwrap::DebuglessUnwrap;
+    use embedded_hal_mock::eh0::{delay::NoopDelay, digital, serial};
+
+    #[test]
+    fn usage_with_embedded_hal_02() {
+        let set_pin = digital::Mock::new(&[
+            digital::Transaction::set(digital::State::High),
+            digital::Transaction::set(digital::State::Low),
+            digital::Transaction::set(digital::State::High),
+        ]);
+        let serial = serial::Mock::new(&[
+            serial::Transaction::read_error(nb::Error::WouldBlock),
+            serial::Transaction::write_many(b"AT\r\n"),
+            serial::Transaction::read_many(b"OK\r\n"),
+            serial::Transaction::write_many(b"AT\r\n"),
+            serial::Transaction::read_error(nb::Error::WouldBlock),
+            serial::Transaction::read_many(b"OK\r\n"),
+            serial::Transaction::write_many(b"data"),
+            serial::Transaction::flush(),
+        ]);
+        let hc12 = super::new(serial, set_pin, NoopDelay::new()).unwrap();
+        let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+        hc12.is_ok().unwrap();
+        let mut hc12 = hc12.into_normal_mode().debugless_unwrap();
+        embedded_io::Write::write_all(&mut hc12, b"data").unwrap();
+        embedded_io::Write::flush(&mut hc12).unwrap();
+        let (serial, set_pin, _) = hc12.release();
+        serial.release().done();
+        set_pin.release().done();
+    }
+}
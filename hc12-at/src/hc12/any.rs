@@ -0,0 +1,322 @@
+//! Hc12 whose mode is only known at runtime.
+//!
+//! The type-state `Hc12` fits straight-line code, but not a struct field or a static whose mode changes,
+//! like a resource shared between interrupt handlers.
+//! `AnyHc12` holds Hc12 in whichever mode it is in and checks the mode on every call.
+//! Calls in the wrong mode fail with `Error::WrongMode`, and a failed transition keeps the previous mode.
+//!
+//! Transitions take `AnyHc12` by value and always hand it back with their outcome, so Hc12 is never lost.
+//! A field or resource which is borrowed mutably can hold `Option<AnyHc12>` and put it back after the transition.
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, parameters::Parameters,
+    serial_format::SerialFormat, transmission_power::TransmissionPower,
+};
+use crate::settings::version::{Capabilities, FirmwareVersion};
+
+use super::timing::Timing;
+use super::{Configuration, Hc12, Hc12Error, ModeKind, Normal, Sleep, Transition, TransitionError};
+
+/// Hc12 in one of its modes
+#[derive(Debug)]
+enum Inner<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    Normal(Hc12<S, P, D, Normal>),
+    Configuration(Hc12<S, P, D, Configuration>),
+    Sleep(Hc12<S, P, D, Sleep>),
+}
+
+/// Evaluate the same expression for Hc12 in whichever mode it is
+macro_rules! each_mode {
+    ($inner:expr, $hc12:ident => $body:expr) => {
+        match $inner {
+            Inner::Normal($hc12) => $body,
+            Inner::Configuration($hc12) => $body,
+            Inner::Sleep($hc12) => $body,
+        }
+    };
+}
+
+/// Hc12 whose mode is checked at runtime
+#[derive(Debug)]
+pub struct AnyHc12<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    inner: Inner<S, P, D>,
+}
+
+/// Hc12 after a transition, with its outcome
+pub type Switched<S, P, D> = (AnyHc12<S, P, D>, Result<(), Hc12Error<S, P>>);
+
+impl<S, P, D> AnyHc12<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Construct an Hc12 in normal mode, see `Hc12::new`
+    pub fn new(serial: S, set_pin: P, delay: D) -> Result<Self, Hc12Error<S, P>> {
+        Hc12::new(serial, set_pin, delay).map(Self::from)
+    }
+
+    /// Release the contained resources, in whichever mode Hc12 is
+    pub fn release(self) -> (S, P, D) {
+        each_mode!(self.inner, hc12 => (hc12.serial, hc12.set_pin, hc12.delay))
+    }
+
+    /// Mode Hc12 is in
+    pub fn current_mode(&self) -> ModeKind {
+        match &self.inner {
+            Inner::Normal(_) => ModeKind::Normal,
+            Inner::Configuration(_) => ModeKind::Configuration,
+            Inner::Sleep(_) => ModeKind::Sleep,
+        }
+    }
+
+    /// Time in milliseconds Hc12 has to respond to a command
+    pub fn timeout_ms(&self) -> u16 {
+        each_mode!(&self.inner, hc12 => hc12.timeout_ms())
+    }
+
+    /// Set the time in milliseconds Hc12 has to respond to a command
+    pub fn set_timeout_ms(&mut self, timeout_ms: u16) {
+        each_mode!(&mut self.inner, hc12 => hc12.set_timeout_ms(timeout_ms))
+    }
+
+    /// Waits around mode transitions
+    pub fn timing(&self) -> &Timing {
+        each_mode!(&self.inner, hc12 => hc12.timing())
+    }
+
+    /// Set the waits around mode transitions
+    pub fn set_timing(&mut self, timing: Timing) {
+        each_mode!(&mut self.inner, hc12 => hc12.set_timing(timing))
+    }
+
+    /// Features of the firmware, as far as they are known
+    pub fn capabilities(&self) -> &Capabilities {
+        each_mode!(&self.inner, hc12 => hc12.capabilities())
+    }
+
+    /// Set the features of the firmware, see `Hc12::set_capabilities`
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        each_mode!(&mut self.inner, hc12 => hc12.set_capabilities(capabilities))
+    }
+
+    /// Parameters as last read from or set on Hc12
+    pub fn known_parameters(&self) -> &Parameters {
+        each_mode!(&self.inner, hc12 => hc12.known_parameters())
+    }
+
+    /// Hc12 in normal mode, for calls which are not forwarded
+    pub fn normal(&mut self) -> Result<&mut Hc12<S, P, D, Normal>, Hc12Error<S, P>> {
+        let mode = self.current_mode();
+        match &mut self.inner {
+            Inner::Normal(hc12) => Ok(hc12),
+            _ => Err(crate::Error::WrongMode(mode)),
+        }
+    }
+
+    /// Hc12 in config mode, for calls which are not forwarded like `Hc12::apply`
+    pub fn configuration(&mut self) -> Result<&mut Hc12<S, P, D, Configuration>, Hc12Error<S, P>> {
+        let mode = self.current_mode();
+        match &mut self.inner {
+            Inner::Configuration(hc12) => Ok(hc12),
+            _ => Err(crate::Error::WrongMode(mode)),
+        }
+    }
+
+    /// Move Hc12 into normal mode, from sleep mode through config mode.
+    /// Does nothing in normal mode.
+    pub fn into_normal_mode(self) -> Switched<S, P, D> {
+        let any = match self.inner {
+            Inner::Sleep(_) => match self.into_configuration_mode() {
+                (any, Ok(())) => any,
+                failed => return failed,
+            },
+            _ => self,
+        };
+        match any.inner {
+            Inner::Configuration(hc12) => {
+                settle(hc12.into_normal_mode(), Inner::Normal, Inner::Configuration)
+            }
+            inner => (Self { inner }, Ok(())),
+        }
+    }
+
+    /// Move Hc12 into config mode.
+    /// Does nothing in config mode.
+    pub fn into_configuration_mode(self) -> Switched<S, P, D> {
+        match self.inner {
+            Inner::Normal(hc12) => settle(
+                hc12.into_configuration_mode(),
+                Inner::Configuration,
+                Inner::Normal,
+            ),
+            Inner::Sleep(hc12) => settle(
+                hc12.into_configuration_mode(),
+                Inner::Configuration,
+                Inner::Sleep,
+            ),
+            inner => (Self { inner }, Ok(())),
+        }
+    }
+
+    /// Move Hc12 into sleep mode, from normal mode through config mode.
+    /// Does nothing in sleep mode.
+    pub fn into_sleeping_mode(self) -> Switched<S, P, D> {
+        let any = match self.inner {
+            Inner::Normal(_) => match self.into_configuration_mode() {
+                (any, Ok(())) => any,
+                failed => return failed,
+            },
+            _ => self,
+        };
+        match any.inner {
+            Inner::Configuration(hc12) => settle(
+                hc12.into_sleeping_mode(),
+                Inner::Sleep,
+                Inner::Configuration,
+            ),
+            inner => (Self { inner }, Ok(())),
+        }
+    }
+
+    /// Write entire buffer to serial port, in normal mode
+    pub fn write_buffer(&mut self, buffer: &[u8]) -> Result<(), Hc12Error<S, P>> {
+        self.normal()?.write_buffer(buffer)
+    }
+
+    /// Read entire buffer from serial port, in normal mode
+    pub fn read_buffer(&mut self, buffer: &mut [u8]) -> Result<(), Hc12Error<S, P>> {
+        self.normal()?.read_buffer(buffer)
+    }
+
+    /// Read from serial port until the buffer is full or `timeout_ms` passed, in normal mode
+    pub fn read_buffer_timeout(
+        &mut self,
+        buffer: &mut [u8],
+        timeout_ms: u16,
+    ) -> Result<usize, Hc12Error<S, P>> {
+        self.normal()?.read_buffer_timeout(buffer, timeout_ms)
+    }
+
+    /// Check if Hc12 responds to "AT" query with "OK", in config mode
+    pub fn is_ok(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.is_ok()
+    }
+
+    /// Get version as a string from Hc12, in config mode
+    pub fn get_version<'a>(
+        &mut self,
+        buffer: &'a mut [u8; 64],
+    ) -> Result<&'a [u8], Hc12Error<S, P>> {
+        self.configuration()?.get_version(buffer)
+    }
+
+    /// Read and parse the firmware version, in config mode
+    pub fn get_firmware_version(&mut self) -> Result<FirmwareVersion, Hc12Error<S, P>> {
+        self.configuration()?.get_firmware_version()
+    }
+
+    /// Reset Hc12 to default settings, in config mode
+    pub fn reset_settings(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.reset_settings()
+    }
+
+    /// Get parameters of Hc12, in config mode
+    pub fn get_parameters(&mut self) -> Result<Parameters, Hc12Error<S, P>> {
+        self.configuration()?.get_parameters()
+    }
+
+    /// Set the baud rate of Hc12, in config mode
+    pub fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.set_baud_rate(rate)
+    }
+
+    /// Set the channel of Hc12, in config mode
+    pub fn set_channel(&mut self, channel: Channel) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.set_channel(channel)
+    }
+
+    /// Set the operation mode of Hc12, in config mode
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.set_mode(mode)
+    }
+
+    /// Set the transmission power of Hc12, in config mode
+    pub fn set_power(&mut self, power: TransmissionPower) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.set_power(power)
+    }
+
+    /// Set the serial data format of Hc12, in config mode
+    pub fn set_serial_format(&mut self, format: SerialFormat) -> Result<(), Hc12Error<S, P>> {
+        self.configuration()?.set_serial_format(format)
+    }
+}
+
+/// Hc12 in its new mode after a successful transition, or in its previous mode with the error
+fn settle<S, P, D, From, To>(
+    transition: Transition<From, To, S, P>,
+    to: fn(To) -> Inner<S, P, D>,
+    from: fn(From) -> Inner<S, P, D>,
+) -> Switched<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    match transition {
+        Ok(hc12) => (AnyHc12 { inner: to(hc12) }, Ok(())),
+        Err(TransitionError { hc12, error }) => (AnyHc12 { inner: from(hc12) }, Err(error)),
+    }
+}
+
+impl<S, P, D> From<Hc12<S, P, D, Normal>> for AnyHc12<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    fn from(hc12: Hc12<S, P, D, Normal>) -> Self {
+        Self {
+            inner: Inner::Normal(hc12),
+        }
+    }
+}
+
+impl<S, P, D> From<Hc12<S, P, D, Configuration>> for AnyHc12<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    fn from(hc12: Hc12<S, P, D, Configuration>) -> Self {
+        Self {
+            inner: Inner::Configuration(hc12),
+        }
+    }
+}
+
+impl<S, P, D> From<Hc12<S, P, D, Sleep>> for AnyHc12<S, P, D>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    fn from(hc12: Hc12<S, P, D, Sleep>) -> Self {
+        Self {
+            inner: Inner::Sleep(hc12),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Apply a complete set of parameters to Hc12, with read-back verification and rollback.
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::settings::parameter::parameters::Parameters;
+
+use super::baud_rate::ReconfigureBaudRate;
+use super::{Configuration, Hc12, Hc12Error, PinError, SerialError};
+
+/// A single parameter of Hc12
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Setting {
+    /// Baud rate
+    BaudRate,
+    /// Communication channel
+    Channel,
+    /// Transmission power
+    Power,
+    /// Operation mode
+    Mode,
+    /// Serial data format
+    SerialFormat,
+}
+
+/// Report of a failed [`Hc12::apply`]
+#[derive(Debug)]
+pub struct ApplyReport<SerialErr, PinErr> {
+    /// Parameters of Hc12 before applying, or `None` if reading them failed
+    pub previous: Option<Parameters>,
+    /// Setting whose command failed, or `None` if reading back or comparing the parameters failed
+    pub failed: Option<Setting>,
+    /// Error which aborted applying
+    pub error: crate::Error<SerialErr, PinErr>,
+    /// Whether the previous parameters were restored and verified
+    pub restored: bool,
+    attempted: [Setting; 5],
+    attempted_count: usize,
+}
+
+impl<SerialErr, PinErr> ApplyReport<SerialErr, PinErr> {
+    /// Settings which were sent to Hc12, in order
+    pub fn attempted(&self) -> &[Setting] {
+        &self.attempted[..self.attempted_count]
+    }
+
+    /// Report of a failure before any setting was sent
+    fn unchanged(
+        previous: Option<Parameters>,
+        failed: Option<Setting>,
+        error: crate::Error<SerialErr, PinErr>,
+    ) -> Self {
+        ApplyReport {
+            previous,
+            failed,
+            error,
+            restored: false,
+            attempted: [Setting::BaudRate; 5],
+            attempted_count: 0,
+        }
+    }
+}
+
+/// Report of a failed apply of an Hc12 with serial port `S` and SET pin `P`
+pub type Hc12ApplyReport<S, P> = ApplyReport<SerialError<S>, PinError<P>>;
+
+/// Implementation of apply for Hc12 in config mode.
+///
+/// Changing the baud rate switches the serial port of Hc12 right away, so the serial port of the host has to follow.
+impl<S, P, D> Hc12<S, P, D, Configuration>
+where
+    S: Read + Write + ReadReady + ReconfigureBaudRate,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Apply all parameters, sending only the settings which differ from the current ones.
+    ///
+    /// The settings are sent in an order such that every intermediate configuration is valid.
+    /// A new baud rate is followed by the serial port, and Hc12 is queried with "AT" at the new baud rate.
+    /// Afterwards, the parameters are read back and compared.
+    /// If any step fails, the previous parameters are restored.
+    ///
+    /// The serial format is not applied, as the serial port cannot follow it.
+    /// A target with a different serial format fails with `Unsupported` before anything is sent.
+    pub fn apply(&mut self, target: &Parameters) -> Result<(), Hc12ApplyReport<S, P>> {
+        self.apply_with(target, true)
+    }
+
+    /// Apply all parameters like `Hc12::apply`.
+    ///
+    /// With `retune` false, a new baud rate is stored in Hc12 without switching the serial port or querying Hc12 at it,
+    /// for Hc12 which keeps talking at 9600 bps until SET goes high after a power cycle.
+    pub(super) fn apply_with(
+        &mut self,
+        target: &Parameters,
+        retune: bool,
+    ) -> Result<(), Hc12ApplyReport<S, P>> {
+        let previous = self
+            .get_parameters()
+            .map_err(|error| ApplyReport::unchanged(None, None, error))?;
+        if let Err(e) = target.validate() {
+            return Err(ApplyReport::unchanged(Some(previous), None, e.widen()));
+        }
+        if target.serial_format != previous.serial_format {
+            return Err(ApplyReport::unchanged(
+                Some(previous),
+                Some(Setting::SerialFormat),
+                crate::Error::Unsupported,
+            ));
+        }
+
+        let mut attempted = [Setting::BaudRate; 5];
+        let mut attempted_count = 0;
+        let mut outcome = Ok(());
+        for setting in &Self::order(&previous, target) {
+            if !Self::differs(*setting, &previous, target) {
+                continue;
+            }
+            attempted[attempted_count] = *setting;
+            attempted_count += 1;
+            if let Err(e) = self.send_setting(*setting, target, retune) {
+                outcome = Err((Some(*setting), e));
+                break;
+            }
+        }
+        let (failed, error) =
+            match outcome.and_then(|()| self.verify(target).map_err(|e| (None, e))) {
+                Ok(()) => return Ok(()),
+                Err(failure) => failure,
+            };
+        let attempted_settings = &attempted[..attempted_count];
+        let restored = self.restore(&previous, attempted_settings, retune);
+        Err(ApplyReport {
+            previous: Some(previous),
+            failed,
+            error,
+            restored,
+            attempted,
+            attempted_count,
+        })
+    }
+
+    /// Baud rate and serial format are sent last, as Hc12 switches its serial port right away.
+    /// Only if the new mode does not allow the current baud rate, the baud rate is sent before it.
+    fn order(current: &Parameters, target: &Parameters) -> [Setting; 5] {
+        let intermediate = Parameters {
+            mode: target.mode,
+            baud_rate: current.baud_rate,
+            ..*current
+        };
+        if intermediate.validate().is_ok() {
+            [
+                Setting::Channel,
+                Setting::Power,
+                Setting::Mode,
+                Setting::BaudRate,
+                Setting::SerialFormat,
+            ]
+        } else {
+            [
+                Setting::Channel,
+                Setting::Power,
+                Setting::BaudRate,
+                Setting::Mode,
+                Setting::SerialFormat,
+            ]
+        }
+    }
+
+    fn differs(setting: Setting, a: &Parameters, b: &Parameters) -> bool {
+        match setting {
+            Setting::BaudRate => a.baud_rate != b.baud_rate,
+            Setting::Channel => a.channel != b.channel,
+            Setting::Power => a.power != b.power,
+            Setting::Mode => a.mode != b.mode,
+            Setting::SerialFormat => a.serial_format != b.serial_format,
+        }
+    }
+
+    fn send_setting(
+        &mut self,
+        setting: Setting,
+        params: &Parameters,
+        retune: bool,
+    ) -> Result<(), Hc12Error<S, P>> {
+        match setting {
+            Setting::BaudRate if retune => self.retune(params.baud_rate),
+            Setting::BaudRate => self.set_baud_rate(params.baud_rate),
+            Setting::Channel => self.set_channel(params.channel),
+            Setting::Power => self.set_power(params.power),
+            Setting::Mode => self.set_mode(params.mode),
+            Setting::SerialFormat => self.set_serial_format(params.serial_format),
+        }
+    }
+
+    /// Read the parameters back and compare them
+    fn verify(&mut self, expected: &Parameters) -> Result<(), Hc12Error<S, P>> {
+        if self.get_parameters()? == *expected {
+            Ok(())
+        } else {
+            Err(crate::Error::Mismatch)
+        }
+    }
+
+    /// Undo the attempted settings in reverse order, then verify.
+    fn restore(&mut self, previous: &Parameters, attempted: &[Setting], retune: bool) -> bool {
+        let mut restored = true;
+        for setting in attempted.iter().rev() {
+            restored &= self.send_setting(*setting, previous, retune).is_ok();
+        }
+        restored && self.verify(previous).is_ok()
+    }
+}
@@ -0,0 +1,117 @@
+//! Detect and change the baud rate of Hc12 by retuning the serial port of the host.
+
+use core::marker::PhantomData;
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+
+use crate::settings::parameter::{baudrate::BaudRate, OK_QUERY, OK_RESPONSE};
+
+use super::state::DriverState;
+use super::timing::Timing;
+use super::{Configuration, Hc12, Hc12Error};
+
+/// Serial port whose baud rate can be changed at runtime
+pub trait ReconfigureBaudRate: ErrorType {
+    /// Switch the serial port to the given baud rate
+    fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Self::Error>;
+}
+
+impl<T: ReconfigureBaudRate + ?Sized> ReconfigureBaudRate for &mut T {
+    fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Self::Error> {
+        T::set_baud_rate(self, rate)
+    }
+}
+
+/// Implementation of baud rate detection for Hc12 in config mode.
+impl<S, P, D> Hc12<S, P, D, Configuration>
+where
+    S: Read + Write + ReadReady + ReconfigureBaudRate,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Construct an Hc12 in config mode by probing all baud rates in ascending order.
+    ///
+    /// At each baud rate, stale input is discarded and Hc12 is queried with "AT".
+    /// Returns Hc12 at the first baud rate where it answered "OK", together with that baud rate.
+    /// Fails with `NoResponse` if Hc12 did not answer at any baud rate.
+    pub fn probe(
+        serial: S,
+        mut set_pin: P,
+        mut delay: D,
+    ) -> Result<(Self, BaudRate), Hc12Error<S, P>> {
+        let timing = Timing::default();
+        set_pin.set_low().map_err(crate::Error::Pin)?;
+        delay.delay_ms(timing.enter_configuration_ms);
+        let mut hc12 = Hc12 {
+            serial,
+            set_pin,
+            delay,
+            state: DriverState::new(timing),
+            mode: PhantomData::<Configuration>,
+        };
+        for rate in BaudRate::ALL {
+            match hc12.answers_at(rate) {
+                Ok(()) => {
+                    hc12.state.parameters.baud_rate = rate;
+                    return Ok((hc12, rate));
+                }
+                // Garbage or silence is expected at the wrong baud rate
+                Err(
+                    crate::Error::Read(_)
+                    | crate::Error::UnexpectedResponse(_)
+                    | crate::Error::NoResponse
+                    | crate::Error::Timeout,
+                ) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(crate::Error::NoResponse)
+    }
+
+    /// Change the baud rate of Hc12 and of the serial port together.
+    ///
+    /// The new baud rate must be valid for the current mode of Hc12.
+    /// After Hc12 acknowledged the new baud rate, the serial port is switched and Hc12 is queried with "AT".
+    /// If Hc12 does not answer, both are moved back to the previous baud rate, confirmed with "AT", and the error is returned.
+    /// If Hc12 does not answer at the previous baud rate either, that error is returned instead.
+    pub fn change_baud_rate(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        let mut params = self.get_parameters()?;
+        let previous = params.baud_rate;
+        params.set_baud_rate(rate).map_err(|e| e.widen())?;
+        let error = match self.retune(rate) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        // Hc12 may have switched anyway, so move it back unless it still answers at the previous rate
+        if self.answers_at(previous).is_err() {
+            self.serial
+                .set_baud_rate(rate)
+                .map_err(crate::Error::Reconfigure)?;
+            self.retune(previous)?;
+        }
+        self.state.parameters.baud_rate = previous;
+        Err(error)
+    }
+
+    /// Set the baud rate of Hc12, then switch the serial port and query Hc12 with "AT".
+    ///
+    /// The serial port is switched even if Hc12 did not acknowledge,
+    /// as Hc12 may have switched anyway and a rollback has to reach it.
+    pub(super) fn retune(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        let acknowledged = self.set_baud_rate(rate);
+        let answered = self.answers_at(rate);
+        acknowledged.and(answered)
+    }
+
+    /// Switch the serial port to `rate`, let Hc12 settle and check whether Hc12 answers "AT" with "OK".
+    pub(super) fn answers_at(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        self.serial
+            .set_baud_rate(rate)
+            .map_err(crate::Error::Reconfigure)?;
+        self.delay.delay_ms(self.state.timing.baud_rate_change_ms);
+        self.discard_input()?;
+        let mut buffer = [0u8; 4];
+        self.expect_response(&OK_QUERY, &OK_RESPONSE, &mut buffer)
+    }
+}
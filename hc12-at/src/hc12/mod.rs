@@ -1,82 +1,316 @@
 //! Type-state driven state machine of Hc12.
 
-use core::convert::TryFrom;
 use core::marker::PhantomData;
 
-use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::{
-    digital::v2::OutputPin,
-    serial::{Read, Write},
-};
-use nb::*;
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
 
 use crate::settings::parameter::{
     baudrate::BaudRate, channel::Channel, mode::Mode, parameters::Parameters,
-    transmission_power::TransmissionPower, OK_QUERY, OK_RESPONSE, QUERY_PARAMS_COMMAND,
-    RESET_SETTINGS_COMMAND, RESET_SETTINGS_RESPONSE, SLEEP_COMMAND, SLEEP_RESPONSE, VERSION_QUERY,
+    serial_format::SerialFormat, transmission_power::TransmissionPower, OK_QUERY, OK_RESPONSE,
+    QUERY_PARAMS_COMMAND, RESET_SETTINGS_COMMAND, RESET_SETTINGS_RESPONSE, SLEEP_COMMAND,
+    SLEEP_RESPONSE, VERSION_QUERY,
 };
+use crate::settings::query::MakeQuery;
+use crate::settings::version::{Capabilities, FirmwareVersion};
+
+use self::state::{check_response, confirm_again, parse_parameters, DriverState, ParameterValue};
+use self::timing::Timing;
+
+/// Hc12 whose mode is only known at runtime
+pub mod any;
+
+/// Apply a complete set of parameters
+pub mod apply;
+
+/// Detect and change the baud rate of the serial port
+pub mod baud_rate;
+
+/// Recover Hc12 with unknown settings
+pub mod recover;
+
+/// Temporary reconfiguration from normal mode
+pub mod scoped;
+
+/// Settle times of mode transitions
+pub mod timing;
+
+/// Bookkeeping shared with the async driver
+pub(crate) mod state;
 
 #[cfg(test)]
 mod test;
 
 /// Normal mode marker
+#[derive(Debug)]
 pub struct Normal;
 
 /// Configuration mode marker
+#[derive(Debug)]
 pub struct Configuration;
 
 /// Sleep mode marker
+#[derive(Debug)]
 pub struct Sleep;
 
+/// Mode of Hc12, for code which tracks it at runtime
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModeKind {
+    /// Normal (transparent) mode
+    Normal,
+    /// Configuration mode
+    Configuration,
+    /// Sleep mode
+    Sleep,
+}
+
+/// Default time in milliseconds Hc12 has to respond to a command
+pub const DEFAULT_TIMEOUT_MS: u16 = 1000;
+
+/// Interval in microseconds in which the serial port is polled while waiting for a response
+const POLL_INTERVAL_US: u32 = 100;
+
+/// Error of the serial port `S`
+type SerialError<S> = <S as ErrorType>::Error;
+
+/// Error of the SET pin `P`
+type PinError<P> = <P as embedded_hal::digital::ErrorType>::Error;
+
+/// Error of an Hc12 with serial port `S` and SET pin `P`
+pub type Hc12Error<S, P> = crate::Error<SerialError<S>, PinError<P>>;
+
+/// Failed transition between modes, returning Hc12 in the mode it was in before
+#[derive(Debug)]
+pub struct TransitionError<H, SerialErr, PinErr> {
+    /// Hc12 in its previous mode
+    pub hc12: H,
+    /// Reason for the failed transition
+    pub error: crate::Error<SerialErr, PinErr>,
+}
+
+/// Fallible transition of an Hc12 with serial port `S` and SET pin `P`
+type Transition<From, To, S, P> =
+    core::result::Result<To, TransitionError<From, SerialError<S>, PinError<P>>>;
+
 /// Fallible transition from normal to config state
 type NormalToConfig<S, P, D> =
-    core::result::Result<Hc12<S, P, D, Configuration>, Hc12<S, P, D, Normal>>;
+    Transition<Hc12<S, P, D, Normal>, Hc12<S, P, D, Configuration>, S, P>;
 
 /// Fallible transition from config to normal state
 type ConfigToNormal<S, P, D> =
-    core::result::Result<Hc12<S, P, D, Normal>, Hc12<S, P, D, Configuration>>;
+    Transition<Hc12<S, P, D, Configuration>, Hc12<S, P, D, Normal>, S, P>;
 
 /// Fallible transition from config to sleep state
-type ConfigToSleep<S, P, D> =
-    core::result::Result<Hc12<S, P, D, Sleep>, Hc12<S, P, D, Configuration>>;
+type ConfigToSleep<S, P, D> = Transition<Hc12<S, P, D, Configuration>, Hc12<S, P, D, Sleep>, S, P>;
 
 /// Fallible transition from sleep to config state
-type SleepToConfig<S, P, D> =
-    core::result::Result<Hc12<S, P, D, Configuration>, Hc12<S, P, D, Sleep>>;
+type SleepToConfig<S, P, D> = Transition<Hc12<S, P, D, Sleep>, Hc12<S, P, D, Configuration>, S, P>;
 
 /// Hc12 resources: A serial port, an output pin, and a Delay.
+///
+/// The serial port must tell whether bytes are ready to be read, so that reads can time out.
 #[derive(Debug)]
 pub struct Hc12<S, P, D, M>
 where
-    S: Read<u8> + Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
     serial: S,
     set_pin: P,
     delay: D,
+    state: DriverState,
     mode: PhantomData<M>,
 }
 
+/// Implementation for all modes of Hc12
+impl<S, P, D, M> Hc12<S, P, D, M>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Time in milliseconds Hc12 has to respond to a command
+    pub fn timeout_ms(&self) -> u16 {
+        self.state.timeout_ms
+    }
+
+    /// Set the time in milliseconds Hc12 has to respond to a command
+    pub fn set_timeout_ms(&mut self, timeout_ms: u16) {
+        self.state.timeout_ms = timeout_ms;
+    }
+
+    /// Waits around mode transitions
+    pub fn timing(&self) -> &Timing {
+        &self.state.timing
+    }
+
+    /// Set the waits around mode transitions, for modules which need longer than the datasheet says
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.state.timing = timing;
+    }
+
+    /// Features of the firmware, as far as they are known.
+    /// Until the firmware version was read, all features are assumed.
+    pub fn capabilities(&self) -> &Capabilities {
+        self.state.capabilities()
+    }
+
+    /// Set the features of the firmware, for firmware whose version is not recognised, like that of clones.
+    /// Reading the firmware version replaces them again.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.state.set_capabilities(capabilities);
+    }
+
+    /// Parameters as last read from or set on Hc12, the defaults until then
+    pub fn known_parameters(&self) -> &Parameters {
+        &self.state.parameters
+    }
+
+    /// Tell the driver the parameters of Hc12, if they are known without reading them
+    pub fn set_known_parameters(&mut self, parameters: Parameters) {
+        self.state.parameters = parameters;
+    }
+
+    /// Change the mode marker, keeping all resources
+    fn into_mode<N>(self) -> Hc12<S, P, D, N> {
+        Hc12 {
+            serial: self.serial,
+            set_pin: self.set_pin,
+            delay: self.delay,
+            state: self.state,
+            mode: PhantomData::<N>,
+        }
+    }
+
+    /// Read a byte, polling the serial port until `waited_us` reaches `timeout_ms`.
+    fn read_byte(&mut self, waited_us: &mut u32, timeout_ms: u16) -> Result<u8, Hc12Error<S, P>> {
+        loop {
+            if self.serial.read_ready().map_err(crate::Error::Read)? {
+                let mut byte = [0u8];
+                if self.serial.read(&mut byte).map_err(crate::Error::Read)? == 1 {
+                    return Ok(byte[0]);
+                }
+            }
+            if *waited_us >= u32::from(timeout_ms) * 1000 {
+                return Err(crate::Error::Timeout);
+            }
+            self.delay.delay_us(POLL_INTERVAL_US);
+            *waited_us += POLL_INTERVAL_US;
+        }
+    }
+
+    /// Pull SET low and confirm that Hc12 accepts commands.
+    ///
+    /// After the wait for config mode, stale input like received radio data is discarded
+    /// and Hc12 is queried with "AT" until it answers "OK".
+    /// When it never does, SET is released again and Hc12 is given `Timing::exit_configuration_ms` to settle.
+    fn enter_configuration(&mut self) -> Result<(), Hc12Error<S, P>> {
+        self.set_pin.set_low().map_err(crate::Error::Pin)?;
+        self.delay
+            .delay_ms(self.state.timing.enter_configuration_ms);
+        if self.state.timing.confirm_attempts == 0 {
+            return Ok(());
+        }
+        let mut result = Err(crate::Error::NoResponse);
+        for attempt in 0..self.state.timing.confirm_attempts {
+            if attempt > 0 {
+                self.delay.delay_ms(self.state.timing.confirm_interval_ms);
+            }
+            result = self.discard_input().and_then(|()| {
+                let mut buffer = [0u8; 4];
+                self.expect_response(&OK_QUERY, &OK_RESPONSE, &mut buffer)
+            });
+            if !confirm_again(&result) {
+                break;
+            }
+        }
+        if result.is_err() {
+            let _ = self.set_pin.set_high();
+            self.delay.delay_ms(self.state.timing.exit_configuration_ms);
+        }
+        result
+    }
+
+    /// Read and drop all bytes which already arrived at the serial port
+    fn discard_input(&mut self) -> Result<(), Hc12Error<S, P>> {
+        let mut byte = [0u8];
+        while self.serial.read_ready().map_err(crate::Error::Read)? {
+            self.serial.read(&mut byte).map_err(crate::Error::Read)?;
+        }
+        Ok(())
+    }
+
+    /// Wrap this Hc12 with the reason why it could not change its mode
+    fn transition_error(
+        self,
+        error: Hc12Error<S, P>,
+    ) -> TransitionError<Self, SerialError<S>, PinError<P>> {
+        TransitionError { hc12: self, error }
+    }
+
+    /// Write all bytes to serial port
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Hc12Error<S, P>> {
+        self.serial.write_all(bytes).map_err(crate::Error::Write)
+    }
+
+    /// Read into buffer until a newline was received or the buffer is full.
+    /// Returns the number of bytes read.
+    ///
+    /// All lines of a response share the timeout through `waited_us`.
+    /// Fails with `NoResponse` if the timeout passed before the first byte of the line.
+    fn read_line(
+        &mut self,
+        buffer: &mut [u8],
+        waited_us: &mut u32,
+    ) -> Result<usize, Hc12Error<S, P>> {
+        let mut count = 0;
+        for v in buffer.iter_mut() {
+            let ch = match self.read_byte(waited_us, self.state.timeout_ms) {
+                Err(crate::Error::Timeout) if count == 0 => return Err(crate::Error::NoResponse),
+                result => result?,
+            };
+            *v = ch;
+            count += 1;
+            if ch == b'\n' {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Send a command and check that Hc12 answers with exactly the expected response.
+    fn expect_response(
+        &mut self,
+        command: &[u8],
+        expected: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Hc12Error<S, P>> {
+        self.write_all(command)?;
+        let count = self.read_line(buffer, &mut 0)?;
+        check_response(&buffer[..count], expected)
+    }
+}
+
 /// Implementation for normal mode of Hc12
 impl<S, P, D> Hc12<S, P, D, Normal>
 where
-    S: Read<u8> + Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
     /// Construct an Hc12 in normal mode
-    pub fn new(serial: S, mut set_pin: P, mut delay: D) -> Self {
-        let _ = set_pin.set_high();
-        delay.delay_ms(20); // TODO which duration?
-                            // TODO read configuration; if it does not work, baud rate is wrong.
-                            // TODO when e-hal supports changing the baud rate, try to probe the right one
-        Self {
+    pub fn new(serial: S, mut set_pin: P, mut delay: D) -> Result<Self, Hc12Error<S, P>> {
+        let timing = Timing::default();
+        set_pin.set_high().map_err(crate::Error::Pin)?;
+        delay.delay_ms(timing.exit_configuration_ms);
+        Ok(Self {
             serial,
             set_pin,
             delay,
+            state: DriverState::new(timing),
             mode: PhantomData::<Normal>,
-        }
+        })
     }
 
     /// Release the contained resources
@@ -84,68 +318,133 @@ where
         (self.serial, self.set_pin, self.delay)
     }
 
-    /// Write entire buffer to serial port
-    pub fn write_buffer(&mut self, buffer: &[u8]) -> Result<(), Error<crate::Error>> {
-        for ch in buffer {
-            let _ = block!(self.serial.write(*ch));
+    /// Write entire buffer to serial port.
+    ///
+    /// In modes which limit the packet length, like FU4, the buffer is one packet:
+    /// a longer buffer fails with `PacketTooLong` and nothing is written.
+    pub fn write_buffer(&mut self, buffer: &[u8]) -> Result<(), Hc12Error<S, P>> {
+        self.state.check_packet(buffer.len())?;
+        self.write_all(buffer)?;
+        self.state.written(buffer.len());
+        Ok(())
+    }
+
+    /// Read entire buffer from serial port.
+    /// Fails with a timeout if the buffer was not filled within the timeout of this Hc12.
+    pub fn read_buffer(&mut self, buffer: &mut [u8]) -> Result<(), Hc12Error<S, P>> {
+        let mut waited_us = 0;
+        for v in buffer.iter_mut() {
+            *v = self.read_byte(&mut waited_us, self.state.timeout_ms)?;
         }
         Ok(())
     }
 
-    /// Read entire buffer from serial port
-    pub fn read_buffer(&mut self, buffer: &mut [u8]) -> Result<(), Error<crate::Error>> {
-        let mut n = 0;
-        while n < buffer.len() {
-            if let Ok(ch) = block!(self.serial.read()) {
-                buffer[n] = ch;
-                n += 1;
+    /// Read from serial port until the buffer is full or `timeout_ms` passed.
+    /// Returns the number of bytes actually received.
+    pub fn read_buffer_timeout(
+        &mut self,
+        buffer: &mut [u8],
+        timeout_ms: u16,
+    ) -> Result<usize, Hc12Error<S, P>> {
+        let mut waited_us = 0;
+        for (count, v) in buffer.iter_mut().enumerate() {
+            match self.read_byte(&mut waited_us, timeout_ms) {
+                Ok(ch) => *v = ch,
+                Err(crate::Error::Timeout) => return Ok(count),
+                Err(e) => return Err(e),
             }
         }
-        Ok(())
+        Ok(buffer.len())
     }
 
-    /// Move Hc12 in normal mode to Hc12 in config mode
+    /// Move Hc12 in normal mode to Hc12 in config mode.
+    ///
+    /// Bytes written before are flushed and given time to go over the air, see `Parameters::transmission_time_ms`.
+    /// Only the last `MAX_PENDING_LEN` bytes are waited for, as the module sent the ones before while they were written.
+    /// Config mode is confirmed with "AT", see `Timing::confirm_attempts`.
+    /// If Hc12 does not answer, for example because SET is miswired or Hc12 is unpowered,
+    /// SET is released and Hc12 is returned in normal mode with the error.
     pub fn into_configuration_mode(mut self) -> NormalToConfig<S, P, D> {
-        let _ = self.set_pin.set_low();
-        self.delay.delay_ms(40); // TODO how long?
-        Ok(Hc12 {
-            serial: self.serial,
-            set_pin: self.set_pin,
-            delay: self.delay,
-            mode: PhantomData::<Configuration>,
-        })
+        if let Err(e) = self.drain() {
+            return Err(self.transition_error(e));
+        }
+        match self.enter_configuration() {
+            Ok(()) => Ok(self.into_mode()),
+            Err(e) => Err(self.transition_error(e)),
+        }
+    }
+}
+
+/// Implementation of draining for Hc12 in normal mode
+impl<S, P, D> Hc12<S, P, D, Normal>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Flush the serial port and wait until Hc12 sent the bytes written since the last transition
+    fn drain(&mut self) -> Result<(), Hc12Error<S, P>> {
+        let Some(time_ms) = self.state.drain_time_ms() else {
+            return Ok(());
+        };
+        self.serial.flush().map_err(crate::Error::Write)?;
+        self.delay.delay_ms(time_ms);
+        self.state.drained();
+        Ok(())
     }
 }
 
+/// Hc12 in normal mode reports the errors of the underlying serial port.
+impl<S, P, D> ErrorType for Hc12<S, P, D, Normal>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    type Error = SerialError<S>;
+}
+
 /// Implement Read for Hc12 in normal mode
 /// This just defers to the underlying serial implementation.
-impl<S, P, D> embedded_hal::serial::Read<u8> for Hc12<S, P, D, Normal>
+impl<S, P, D> Read for Hc12<S, P, D, Normal>
 where
-    S: Read<u8> + Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
-    type Error = <S as Read<u8>>::Error;
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.serial.read(buf)
+    }
+}
 
-    fn read(&mut self) -> nb::Result<u8, Self::Error> {
-        self.serial.read()
+/// Implement ReadReady for Hc12 in normal mode
+/// This just defers to the underlying serial implementation.
+impl<S, P, D> ReadReady for Hc12<S, P, D, Normal>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.serial.read_ready()
     }
 }
 
 /// Implement Write for Hc12 in normal mode.
 /// This just defers to the underlying serial implementation.
-impl<S, P, D> embedded_hal::serial::Write<u8> for Hc12<S, P, D, Normal>
+impl<S, P, D> Write for Hc12<S, P, D, Normal>
 where
-    S: embedded_hal::serial::Read<u8> + embedded_hal::serial::Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
-    type Error = <S as Write<u8>>::Error;
-    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-        self.serial.write(word)
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let count = self.serial.write(buf)?;
+        self.state.written(count);
+        Ok(count)
     }
 
-    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+    fn flush(&mut self) -> Result<(), Self::Error> {
         self.serial.flush()
     }
 }
@@ -153,152 +452,164 @@ where
 /// Implementation for Hc12 in config mode.
 impl<S, P, D> Hc12<S, P, D, Configuration>
 where
-    S: Read<u8> + Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
     /// Move Hc12 in config mode back to normal mode.
     pub fn into_normal_mode(mut self) -> ConfigToNormal<S, P, D> {
-        let _ = self.set_pin.set_high();
-        self.delay.delay_ms(40); // TODO how long?
-        Ok(Hc12 {
-            serial: self.serial,
-            set_pin: self.set_pin,
-            delay: self.delay,
-            mode: PhantomData::<Normal>,
-        })
+        if let Err(e) = self.set_pin.set_high() {
+            return Err(self.transition_error(crate::Error::Pin(e)));
+        }
+        self.delay.delay_ms(self.state.timing.exit_configuration_ms);
+        Ok(self.into_mode())
     }
 
     /// Move Hc12 in config mode into sleeping mode.
     pub fn into_sleeping_mode(mut self) -> ConfigToSleep<S, P, D> {
-        for ch in &SLEEP_COMMAND {
-            let _ = block!(self.serial.write(*ch));
-        }
-        let mut n = 0;
         let mut response = [0u8; 10];
-        for i in &mut response {
-            if let Ok(ch) = block!(self.serial.read()) {
-                *i = ch;
-                n += 1;
-                if ch == b'\n' {
-                    break;
-                }
-            }
+        if let Err(e) = self.expect_response(&SLEEP_COMMAND, &SLEEP_RESPONSE, &mut response) {
+            return Err(self.transition_error(e));
         }
-        if n == SLEEP_RESPONSE.len() && response[..n] == SLEEP_RESPONSE[..n] {
-            let _ = self.set_pin.set_high();
-            Ok(Hc12 {
-                delay: self.delay,
-                mode: PhantomData::<Sleep>,
-                set_pin: self.set_pin,
-                serial: self.serial,
-            })
-        } else {
-            Err(self)
+        if let Err(e) = self.set_pin.set_high() {
+            return Err(self.transition_error(crate::Error::Pin(e)));
         }
+        self.delay.delay_ms(self.state.timing.exit_configuration_ms);
+        Ok(self.into_mode())
     }
 
     /// Check if Hc12 responds to "AT" query with "OK".
-    pub fn is_ok(&mut self) -> bool {
-        for ch in &OK_QUERY {
-            let _ = block!(self.serial.write(*ch));
-        }
-        let mut n = 0;
+    pub fn is_ok(&mut self) -> Result<(), Hc12Error<S, P>> {
         let mut buffer = [0u8; 4];
-        while n < 4 {
-            if let Ok(ch) = block!(self.serial.read()) {
-                buffer[n] = ch;
-                n += 1;
-            }
-        }
-        buffer == OK_RESPONSE
+        self.expect_response(&OK_QUERY, &OK_RESPONSE, &mut buffer)
     }
 
     /// Get version as a string from Hc12
-    pub fn get_version<'a>(&mut self, buffer: &'a mut [u8; 64]) -> &'a [u8] {
-        for ch in &VERSION_QUERY {
-            let _ = block!(self.serial.write(*ch));
-        }
-        let mut count = 0;
-        for v in buffer.iter_mut() {
-            if let Ok(ch) = block!(self.serial.read()) {
-                *v = ch;
-                count += 1;
-                if ch == b'\n' {
-                    break;
-                }
-            }
-        }
-        &buffer[..count]
+    pub fn get_version<'a>(
+        &mut self,
+        buffer: &'a mut [u8; 64],
+    ) -> Result<&'a [u8], Hc12Error<S, P>> {
+        self.write_all(&VERSION_QUERY)?;
+        let count = self.read_line(buffer, &mut 0)?;
+        Ok(&buffer[..count])
+    }
+
+    /// Get the firmware version of Hc12.
+    ///
+    /// Afterwards, commands the firmware does not implement fail with `Unsupported`,
+    /// and `get_parameters` also queries the serial format if the firmware implements "AT+U".
+    pub fn get_firmware_version(&mut self) -> Result<FirmwareVersion, Hc12Error<S, P>> {
+        let mut buffer = [0u8; 64];
+        let line = self.get_version(&mut buffer)?;
+        self.state.record_version(line)
     }
 
     /// Reset Hc12 to default settings.
-    pub fn reset_settings(&mut self) -> bool {
-        for ch in &RESET_SETTINGS_COMMAND {
-            let _ = block!(self.serial.write(*ch));
-        }
+    pub fn reset_settings(&mut self) -> Result<(), Hc12Error<S, P>> {
         let mut response = [0u8; 12];
-        let mut count = 0;
-        for v in &mut response {
-            if let Ok(ch) = block!(self.serial.read()) {
-                *v = ch;
-                count += 1;
-                if ch == b'\n' {
-                    break;
-                }
-            }
-        }
-        count == RESET_SETTINGS_RESPONSE.len()
-            && response[..count] == RESET_SETTINGS_RESPONSE[..count]
+        self.expect_response(
+            &RESET_SETTINGS_COMMAND,
+            &RESET_SETTINGS_RESPONSE,
+            &mut response,
+        )?;
+        self.state.record_reset();
+        Ok(())
     }
 
-    /// Get parameters of Hc12
-    pub fn get_parameters(&mut self) -> Option<Parameters> {
-        for ch in &QUERY_PARAMS_COMMAND {
-            let _ = block!(self.serial.write(*ch));
-        }
+    /// Get parameters of Hc12.
+    ///
+    /// Until the firmware version was read, the serial format is not queried, and the one last set is reported.
+    pub fn get_parameters(&mut self) -> Result<Parameters, Hc12Error<S, P>> {
+        self.write_all(&QUERY_PARAMS_COMMAND)?;
         let mut params = [[0u8; 16]; 4];
-        let mut param_slices: [&[u8]; 4] = Default::default();
-        for (pi, p) in &mut params.iter_mut().enumerate() {
-            for (i, v) in p.iter_mut().enumerate() {
-                if let Ok(ch) = block!(self.serial.read()) {
-                    *v = ch;
-                    if ch == b'\n' {
-                        param_slices[pi] = &p[..=i];
-                        break;
-                    }
-                }
-            }
+        let mut counts = [0usize; 4];
+        let mut waited_us = 0;
+        for (i, (p, count)) in params.iter_mut().zip(counts.iter_mut()).enumerate() {
+            *count = match self.read_line(p, &mut waited_us) {
+                Err(crate::Error::NoResponse) if i > 0 => return Err(crate::Error::Timeout),
+                result => result?,
+            };
         }
-        let baud_rate = BaudRate::try_from(param_slices[0]).ok()?;
-        let channel = Channel::try_from(param_slices[1]).ok()?;
-        let power = TransmissionPower::try_from(param_slices[2]).ok()?;
-        let mode = Mode::try_from(param_slices[3]).ok()?;
-        Some(Parameters {
-            baud_rate,
-            channel,
-            power,
-            mode,
-        })
+        let parameters = parse_parameters([
+            &params[0][..counts[0]],
+            &params[1][..counts[1]],
+            &params[2][..counts[2]],
+            &params[3][..counts[3]],
+        ])?;
+        let mut format = [0u8; 16];
+        let response = if self.state.queries_serial_format() {
+            let mut query = [0u8; 7];
+            SerialFormat::make_query(&mut query);
+            self.write_all(&query)?;
+            Some(
+                self.read_line(&mut format, &mut 0)
+                    .map(|count| &format[..count]),
+            )
+        } else {
+            None
+        };
+        self.state.record_parameters(parameters, response)
+    }
+
+    /// Set the baud rate of Hc12 and verify the acknowledgement.
+    ///
+    /// Only Hc12 is changed: the serial port of the host keeps its baud rate and has to follow.
+    /// The new baud rate takes effect at the latest when Hc12 leaves config mode, and right away with some firmware.
+    /// After a power-up with SET low, it only takes effect when Hc12 leaves config mode, see `Hc12::recover`.
+    /// `Hc12::change_baud_rate` and `Hc12::apply` switch the serial port of the host along.
+    pub fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&rate)
+    }
+
+    /// Set the channel of Hc12 and verify the acknowledgement.
+    pub fn set_channel(&mut self, channel: Channel) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&channel)
+    }
+
+    /// Set the operation mode of Hc12 and verify the acknowledgement.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&mode)
+    }
+
+    /// Set the transmission power of Hc12 and verify the acknowledgement.
+    pub fn set_power(&mut self, power: TransmissionPower) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&power)
+    }
+
+    /// Set the serial data format of Hc12 and verify the acknowledgement.
+    ///
+    /// Hc12 switches its serial port to the new format right away, so the host has to follow.
+    pub fn set_serial_format(&mut self, format: SerialFormat) -> Result<(), Hc12Error<S, P>> {
+        self.set_parameter(&format)
+    }
+
+    /// Send the command for a parameter, then check that Hc12 acknowledged the same value.
+    /// Fails with `Unsupported` before sending if the firmware does not implement the value.
+    fn set_parameter<T: ParameterValue>(&mut self, value: &T) -> Result<(), Hc12Error<S, P>> {
+        self.state.check_supported(value)?;
+        let mut command = [0u8; 16];
+        let command = value.make_command(&mut command);
+        self.write_all(command)?;
+        let mut response = [0u8; 16];
+        let count = self.read_line(&mut response, &mut 0)?;
+        self.state.record_setting(value, &response[..count])
     }
 }
 
 /// Implementation for Hc12 in sleeping mode.
 impl<S, P, D> Hc12<S, P, D, Sleep>
 where
-    S: Read<u8> + Write<u8>,
+    S: Read + Write + ReadReady,
     P: OutputPin,
-    D: DelayMs<u16>,
+    D: DelayNs,
 {
     /// Move Hc12 in sleeping mode back to configuration mode.
+    ///
+    /// Config mode is confirmed like when coming from normal mode.
     pub fn into_configuration_mode(mut self) -> SleepToConfig<S, P, D> {
-        let _ = self.set_pin.set_low();
-        self.delay.delay_ms(40); // TODO how long?
-        Ok(Hc12 {
-            serial: self.serial,
-            set_pin: self.set_pin,
-            delay: self.delay,
-            mode: PhantomData::<Configuration>,
-        })
+        match self.enter_configuration() {
+            Ok(()) => Ok(self.into_mode()),
+            Err(e) => Err(self.transition_error(e)),
+        }
     }
 }
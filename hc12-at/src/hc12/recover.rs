@@ -0,0 +1,145 @@
+//! Recover Hc12 with unknown settings through the factory path.
+//!
+//! If SET is low while power is applied, Hc12 starts in config mode at 9600 bps, whatever baud rate it stored.
+//! It keeps talking at 9600 bps until SET goes high, and uses its stored baud rate afterwards.
+
+use core::marker::PhantomData;
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::settings::parameter::{baudrate::BaudRate, parameters::Parameters};
+
+use super::baud_rate::ReconfigureBaudRate;
+use super::state::DriverState;
+use super::timing::Timing;
+use super::{Configuration, Hc12, Hc12Error, PinError, SerialError};
+
+/// Number of "AT" queries while waiting for Hc12 to be power-cycled by hand
+const MANUAL_ATTEMPTS: usize = 10;
+
+/// What to do once Hc12 answers at 9600 bps
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// Keep the stored parameters
+    Keep,
+    /// Reset to factory defaults with "AT+DEFAULT"
+    Reset,
+    /// Apply the given parameters
+    Apply(Parameters),
+}
+
+/// Failed `Hc12::recover` with a power pin whose error is `PowerErr`
+#[derive(Debug)]
+pub enum RecoverError<SerialErr, PinErr, PowerErr> {
+    /// Switching the power of Hc12 failed
+    Power(PowerErr),
+    /// Hc12 did not answer, or the recovery action failed
+    Hc12(crate::Error<SerialErr, PinErr>),
+}
+
+impl<SerialErr, PinErr, PowerErr> From<crate::Error<SerialErr, PinErr>>
+    for RecoverError<SerialErr, PinErr, PowerErr>
+{
+    fn from(e: crate::Error<SerialErr, PinErr>) -> Self {
+        RecoverError::Hc12(e)
+    }
+}
+
+/// Failed recovery of an Hc12 with serial port `S` and SET pin `P`, whose power is switched by pin `W`
+pub type Hc12RecoverError<S, P, W> =
+    RecoverError<SerialError<S>, PinError<P>, <W as embedded_hal::digital::ErrorType>::Error>;
+
+/// Implementation of recovery for Hc12 in config mode.
+impl<S, P, D> Hc12<S, P, D, Configuration>
+where
+    S: Read + Write + ReadReady + ReconfigureBaudRate,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Construct an Hc12 in config mode by power-cycling it with SET low.
+    ///
+    /// `power_pin` powers Hc12 while high. It is switched off and on again, see `Timing::power_off_ms`.
+    /// `timing` is used for the power cycle and kept by the returned driver.
+    /// Once Hc12 answered "AT", the recovery action is carried out and verified.
+    ///
+    /// Hc12 keeps talking at 9600 bps while in config mode, so the serial port is left at 9600 bps,
+    /// and a new baud rate of `Recovery::Apply` is only stored in Hc12.
+    /// After SET goes high, Hc12 uses its stored baud rate, so the host has to switch its serial port
+    /// to `known_parameters().baud_rate` when leaving config mode.
+    /// The stored baud rate is the default after `Recovery::Reset` and unknown after `Recovery::Keep`.
+    pub fn recover<W: OutputPin>(
+        serial: S,
+        mut set_pin: P,
+        mut delay: D,
+        power_pin: &mut W,
+        timing: Timing,
+        recovery: Recovery,
+    ) -> Result<Self, Hc12RecoverError<S, P, W>> {
+        set_pin.set_low().map_err(crate::Error::Pin)?;
+        power_pin.set_low().map_err(RecoverError::Power)?;
+        delay.delay_ms(timing.power_off_ms);
+        power_pin.set_high().map_err(RecoverError::Power)?;
+        delay.delay_ms(timing.power_on_ms);
+        Ok(Self::recover_at_factory_rate(
+            serial, set_pin, delay, timing, 1, recovery,
+        )?)
+    }
+
+    /// Construct an Hc12 in config mode while it is power-cycled by hand with SET low.
+    ///
+    /// Hc12 is queried at 9600 bps for several timeouts, then the recovery action is carried out like in `Hc12::recover`.
+    pub fn recover_by_hand(
+        serial: S,
+        mut set_pin: P,
+        delay: D,
+        timing: Timing,
+        recovery: Recovery,
+    ) -> Result<Self, Hc12Error<S, P>> {
+        set_pin.set_low().map_err(crate::Error::Pin)?;
+        Self::recover_at_factory_rate(serial, set_pin, delay, timing, MANUAL_ATTEMPTS, recovery)
+    }
+
+    /// Query Hc12 at 9600 bps up to `attempts` times, then carry out the recovery action
+    fn recover_at_factory_rate(
+        serial: S,
+        set_pin: P,
+        delay: D,
+        timing: Timing,
+        attempts: usize,
+        recovery: Recovery,
+    ) -> Result<Self, Hc12Error<S, P>> {
+        let mut hc12 = Hc12 {
+            serial,
+            set_pin,
+            delay,
+            state: DriverState::new(timing),
+            mode: PhantomData::<Configuration>,
+        };
+        let mut answer = hc12.answers_at(BaudRate::Bps9600);
+        for _ in 1..attempts {
+            if !matches!(
+                answer,
+                Err(crate::Error::NoResponse | crate::Error::UnexpectedResponse(_))
+            ) {
+                break;
+            }
+            answer = hc12.answers_at(BaudRate::Bps9600);
+        }
+        answer?;
+
+        match recovery {
+            Recovery::Keep => {}
+            Recovery::Reset => {
+                hc12.reset_settings()?;
+                if hc12.get_parameters()? != Parameters::default() {
+                    return Err(crate::Error::Mismatch);
+                }
+            }
+            Recovery::Apply(target) => hc12
+                .apply_with(&target, false)
+                .map_err(|report| report.error)?,
+        }
+        Ok(hc12)
+    }
+}
@@ -0,0 +1,69 @@
+//! Temporary reconfiguration of Hc12 in normal mode.
+//!
+//! `Hc12::with_configuration` enters config mode, runs a closure and always tries to return to normal mode,
+//! so changing a setting between transmissions needs no `match` on every transition.
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
+use embedded_io::{Read, ReadReady, Write};
+
+use super::{Configuration, Hc12, Normal, PinError, SerialError, TransitionError};
+
+/// Failed `Hc12::with_configuration` of an Hc12 with serial port `S`, SET pin `P` and delay `D`,
+/// whose closure returns `Result<T, E>`
+#[derive(Debug)]
+pub enum ConfigurationError<S, P, D, T, E>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Config mode was not entered, so the closure did not run.
+    /// Holds Hc12 in normal mode.
+    Enter(TransitionError<Hc12<S, P, D, Normal>, SerialError<S>, PinError<P>>),
+    /// The closure failed, and Hc12 is back in normal mode
+    Closure {
+        /// Hc12 in normal mode
+        hc12: Hc12<S, P, D, Normal>,
+        /// Error of the closure
+        error: E,
+    },
+    /// Hc12 did not return to normal mode after the closure ran.
+    /// Holds Hc12 in config mode with the reason, and the result of the closure.
+    Exit {
+        /// Hc12 in config mode and why it stayed there
+        failed: TransitionError<Hc12<S, P, D, Configuration>, SerialError<S>, PinError<P>>,
+        /// Result of the closure
+        result: Result<T, E>,
+    },
+}
+
+/// Outcome of `Hc12::with_configuration`: Hc12 back in normal mode with the value of the closure
+pub type WithConfiguration<S, P, D, T, E> =
+    Result<(Hc12<S, P, D, Normal>, T), ConfigurationError<S, P, D, T, E>>;
+
+/// Implementation of temporary reconfiguration for Hc12 in normal mode
+impl<S, P, D> Hc12<S, P, D, Normal>
+where
+    S: Read + Write + ReadReady,
+    P: OutputPin,
+    D: DelayNs,
+{
+    /// Enter config mode, run `f` and return to normal mode, even if `f` failed.
+    ///
+    /// Returns Hc12 in normal mode with the value of `f`.
+    /// On failure, the error tells which step failed and holds Hc12 in the mode it ended up in.
+    pub fn with_configuration<T, E, F>(self, f: F) -> WithConfiguration<S, P, D, T, E>
+    where
+        F: FnOnce(&mut Hc12<S, P, D, Configuration>) -> Result<T, E>,
+    {
+        let mut hc12 = self
+            .into_configuration_mode()
+            .map_err(ConfigurationError::Enter)?;
+        let result = f(&mut hc12);
+        match (hc12.into_normal_mode(), result) {
+            (Ok(hc12), Ok(value)) => Ok((hc12, value)),
+            (Ok(hc12), Err(error)) => Err(ConfigurationError::Closure { hc12, error }),
+            (Err(failed), result) => Err(ConfigurationError::Exit { failed, result }),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Bookkeeping shared by the blocking and the async driver.
+//!
+//! Nothing here touches the serial port, the SET pin or the delay:
+//! the drivers send the commands and wait, then hand the responses over to be checked and recorded.
+
+use core::convert::TryFrom;
+
+use crate::settings::command::MakeCommand;
+use crate::settings::parameter::{
+    baudrate::BaudRate,
+    channel::Channel,
+    mode::Mode,
+    parameters::{Parameters, MAX_PENDING_LEN},
+    serial_format::SerialFormat,
+    transmission_power::TransmissionPower,
+    ERROR_RESPONSE,
+};
+use crate::settings::response_parser::ParseAcknowledgement;
+use crate::settings::version::{Capabilities, FirmwareVersion};
+use crate::{Error, RawResponse};
+
+use super::timing::Timing;
+use super::DEFAULT_TIMEOUT_MS;
+
+/// Value of a parameter which is set with a single command
+pub(crate) trait ParameterValue: MakeCommand + ParseAcknowledgement + PartialEq {
+    /// Whether the firmware implements this value
+    fn supported(&self, _capabilities: &Capabilities) -> bool {
+        true
+    }
+
+    /// Record the value as set
+    fn store(&self, parameters: &mut Parameters);
+}
+
+impl ParameterValue for BaudRate {
+    fn store(&self, parameters: &mut Parameters) {
+        parameters.baud_rate = *self;
+    }
+}
+
+impl ParameterValue for Channel {
+    fn store(&self, parameters: &mut Parameters) {
+        parameters.channel = *self;
+    }
+}
+
+impl ParameterValue for Mode {
+    fn supported(&self, capabilities: &Capabilities) -> bool {
+        capabilities.supports_mode(*self)
+    }
+
+    fn store(&self, parameters: &mut Parameters) {
+        parameters.mode = *self;
+    }
+}
+
+impl ParameterValue for TransmissionPower {
+    fn supported(&self, capabilities: &Capabilities) -> bool {
+        capabilities.supports_power(*self)
+    }
+
+    fn store(&self, parameters: &mut Parameters) {
+        parameters.power = *self;
+    }
+}
+
+impl ParameterValue for SerialFormat {
+    fn supported(&self, capabilities: &Capabilities) -> bool {
+        capabilities.serial_format
+    }
+
+    fn store(&self, parameters: &mut Parameters) {
+        parameters.serial_format = *self;
+    }
+}
+
+/// What the driver knows about Hc12, apart from its mode
+#[derive(Debug)]
+pub(crate) struct DriverState {
+    /// Time in milliseconds Hc12 has to respond to a command
+    pub(crate) timeout_ms: u16,
+    pub(crate) timing: Timing,
+    /// Parameters as last read from or set on Hc12
+    pub(crate) parameters: Parameters,
+    /// Features of the firmware, `None` until the firmware version was read
+    capabilities: Option<Capabilities>,
+    /// Bytes written in normal mode which Hc12 may not have sent yet
+    unsent: usize,
+}
+
+impl DriverState {
+    /// State of an Hc12 nothing is known about yet
+    pub(crate) fn new(timing: Timing) -> Self {
+        Self {
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            timing,
+            parameters: Parameters::default(),
+            capabilities: None,
+            unsent: 0,
+        }
+    }
+
+    /// Features of the firmware, all of them until the firmware version was read
+    pub(crate) fn capabilities(&self) -> &Capabilities {
+        self.capabilities.as_ref().unwrap_or(&Capabilities::ALL)
+    }
+
+    /// Set the features of the firmware
+    pub(crate) fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = Some(capabilities);
+    }
+
+    /// Check that a buffer fits into a packet, before it is written
+    pub(crate) fn check_packet<SerialErr, PinErr>(
+        &self,
+        len: usize,
+    ) -> Result<(), Error<SerialErr, PinErr>> {
+        self.parameters
+            .validate_packet_len(len)
+            .map_err(|e| e.widen())
+    }
+
+    /// Record bytes written in normal mode.
+    /// Only the last `MAX_PENDING_LEN` of them are still held by Hc12.
+    pub(crate) fn written(&mut self, count: usize) {
+        self.unsent = (self.unsent + count).min(MAX_PENDING_LEN);
+    }
+
+    /// Time in milliseconds Hc12 needs to send the bytes written so far, `None` if there are none
+    pub(crate) fn drain_time_ms(&self) -> Option<u32> {
+        (self.unsent > 0).then(|| self.parameters.transmission_time_ms(self.unsent))
+    }
+
+    /// Record that Hc12 sent all bytes written so far
+    pub(crate) fn drained(&mut self) {
+        self.unsent = 0;
+    }
+
+    /// Parse the answer to the version query and record the features of the firmware
+    pub(crate) fn record_version<SerialErr, PinErr>(
+        &mut self,
+        line: &[u8],
+    ) -> Result<FirmwareVersion, Error<SerialErr, PinErr>> {
+        let version = FirmwareVersion::try_from(line)?;
+        self.capabilities = Some(version.capabilities());
+        Ok(version)
+    }
+
+    /// Record that Hc12 was reset to default settings
+    pub(crate) fn record_reset(&mut self) {
+        self.parameters = Parameters::default();
+    }
+
+    /// Whether the serial format is queried along with the other parameters.
+    /// Only firmware known to implement "AT+U" is asked.
+    pub(crate) fn queries_serial_format(&self) -> bool {
+        self.capabilities.is_some_and(|c| c.serial_format)
+    }
+
+    /// Record the parameters read from Hc12, with the answer to the serial format query if it was sent
+    pub(crate) fn record_parameters<SerialErr, PinErr>(
+        &mut self,
+        mut parameters: Parameters,
+        format: Option<Result<&[u8], Error<SerialErr, PinErr>>>,
+    ) -> Result<Parameters, Error<SerialErr, PinErr>> {
+        let known = self.parameters.serial_format;
+        parameters.serial_format = match format {
+            Some(response) => parse_serial_format(response, known)?,
+            None => known,
+        };
+        self.parameters = parameters;
+        Ok(parameters)
+    }
+
+    /// Check that the firmware implements a value, before its command is sent
+    pub(crate) fn check_supported<T, SerialErr, PinErr>(
+        &self,
+        value: &T,
+    ) -> Result<(), Error<SerialErr, PinErr>>
+    where
+        T: ParameterValue,
+    {
+        if value.supported(self.capabilities()) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported)
+        }
+    }
+
+    /// Check that Hc12 acknowledged exactly the value which was set, then record it
+    pub(crate) fn record_setting<T, SerialErr, PinErr>(
+        &mut self,
+        value: &T,
+        response: &[u8],
+    ) -> Result<(), Error<SerialErr, PinErr>>
+    where
+        T: ParameterValue,
+    {
+        let acknowledged = T::parse_acknowledgement(response)
+            .ok_or_else(|| Error::UnexpectedResponse(RawResponse::new(response)))?;
+        if acknowledged != *value {
+            return Err(Error::Mismatch);
+        }
+        value.store(&mut self.parameters);
+        Ok(())
+    }
+}
+
+/// Whether confirming config mode is worth another "AT" after this result.
+/// Hc12 may still be settling, so silence and garbage are retried, but not failures of the host.
+pub(crate) fn confirm_again<SerialErr, PinErr>(
+    result: &Result<(), Error<SerialErr, PinErr>>,
+) -> bool {
+    matches!(
+        result,
+        Err(Error::NoResponse | Error::Timeout | Error::UnexpectedResponse(_))
+    )
+}
+
+/// Check that Hc12 answered with exactly the expected response
+pub(crate) fn check_response<SerialErr, PinErr>(
+    response: &[u8],
+    expected: &[u8],
+) -> Result<(), Error<SerialErr, PinErr>> {
+    if response == expected {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedResponse(RawResponse::new(response)))
+    }
+}
+
+/// Parse the four lines Hc12 answers to the parameter query.
+/// The serial format is not part of them, so it is left at its default.
+pub(crate) fn parse_parameters<SerialErr, PinErr>(
+    lines: [&[u8]; 4],
+) -> Result<Parameters, Error<SerialErr, PinErr>> {
+    Ok(Parameters {
+        baud_rate: BaudRate::try_from(lines[0])?,
+        channel: Channel::try_from(lines[1])?,
+        power: TransmissionPower::try_from(lines[2])?,
+        mode: Mode::try_from(lines[3])?,
+        serial_format: SerialFormat::default(),
+    })
+}
+
+/// Parse the answer to the serial format query.
+///
+/// The datasheet does not list "AT+RU" among the queries, so firmware may reject it or not answer at all.
+/// Then the format is unknown and `known` is kept.
+fn parse_serial_format<SerialErr, PinErr>(
+    response: Result<&[u8], Error<SerialErr, PinErr>>,
+    known: SerialFormat,
+) -> Result<SerialFormat, Error<SerialErr, PinErr>> {
+    match response {
+        Ok(line) if line == ERROR_RESPONSE => Ok(known),
+        Ok(line) => Ok(SerialFormat::try_from(line)?),
+        Err(Error::NoResponse) => Ok(known),
+        Err(e) => Err(e),
+    }
+}
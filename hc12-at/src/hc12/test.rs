@@ -1,10 +1,107 @@
+use crate::settings::parameter::parameters::FU4_MAX_PACKET_LEN;
+use crate::settings::parameter::serial_format::{Parity, SerialFormat, StopBits};
 use crate::settings::parameter::transmission_power::TransmissionPower;
+use crate::settings::response_parser::ResponseError;
+
+use super::any::AnyHc12;
+use super::apply::Setting;
+use super::baud_rate::ReconfigureBaudRate;
+use super::recover::Recovery;
+use super::scoped::ConfigurationError;
+use super::timing::Timing;
 
 use super::*;
 
-use debugless_unwrap::DebuglessUnwrap;
-use embedded_hal_mock::pin::*;
-use embedded_hal_mock::{delay::MockNoop, pin, serial};
+use debugless_unwrap::{DebuglessUnwrap, DebuglessUnwrapErr};
+use embedded_hal_mock::eh1::digital::State;
+use embedded_hal_mock::eh1::{
+    delay::{CheckedDelay, NoopDelay as MockNoop, Transaction as DelayTransaction},
+    digital as pin, serial,
+};
+use embedded_hal_nb::serial::{ErrorKind, Read as _};
+
+/// Error of the serial mock
+#[derive(Debug)]
+struct SerialError(ErrorKind);
+
+impl embedded_io::Error for SerialError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::Other
+    }
+}
+
+/// embedded-io view of the serial mock, which is based on embedded-hal-nb
+struct Serial {
+    mock: serial::Mock<u8>,
+    pending: Option<u8>,
+    baud_rates: Vec<BaudRate>,
+}
+
+impl Serial {
+    fn new(transactions: &[serial::Transaction<u8>]) -> Self {
+        Self {
+            mock: serial::Mock::new(transactions),
+            pending: None,
+            baud_rates: Vec::new(),
+        }
+    }
+
+    fn done(&mut self) {
+        self.mock.done();
+    }
+}
+
+impl embedded_io::ErrorType for Serial {
+    type Error = SerialError;
+}
+
+impl embedded_io::ReadReady for Serial {
+    fn read_ready(&mut self) -> core::result::Result<bool, Self::Error> {
+        if self.pending.is_none() {
+            match self.mock.read() {
+                Ok(byte) => self.pending = Some(byte),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(e)) => return Err(SerialError(e)),
+            }
+        }
+        Ok(self.pending.is_some())
+    }
+}
+
+impl embedded_io::Read for Serial {
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        while !embedded_io::ReadReady::read_ready(self)? {}
+        buf[0] = self.pending.take().unwrap();
+        Ok(1)
+    }
+}
+
+impl ReconfigureBaudRate for Serial {
+    fn set_baud_rate(&mut self, rate: BaudRate) -> core::result::Result<(), Self::Error> {
+        self.baud_rates.push(rate);
+        Ok(())
+    }
+}
+
+impl embedded_io::Write for Serial {
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        for byte in buf {
+            nb::block!(embedded_hal_nb::serial::Write::write(&mut self.mock, *byte))
+                .map_err(SerialError)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        nb::block!(embedded_hal_nb::serial::Write::flush(&mut self.mock)).map_err(SerialError)
+    }
+}
+
+/// Reads which find no data for `timeout_ms`, so that the driver times out
+fn silence(timeout_ms: u32) -> impl Iterator<Item = serial::Transaction<u8>> {
+    let polls = timeout_ms * 1000 / POLL_INTERVAL_US + 1;
+    (0..polls).map(|_| serial::Transaction::read_error(nb::Error::WouldBlock))
+}
 
 #[test]
 fn is_ok() {
@@ -16,14 +113,16 @@ fn is_ok() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT\r\n"),
         serial::Transaction::read_many(b"OK\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
-    let ok = hc12.is_ok();
-    assert!(ok);
+    hc12.is_ok().unwrap();
     let hc12 = hc12.into_normal_mode().debugless_unwrap();
     let (mut serial, mut set_pin, _) = hc12.release();
     serial.done();
@@ -35,21 +134,44 @@ fn send_buffer() {
     let delay = MockNoop;
     let set_pin = pin::Mock::new(&[pin::Transaction::set(State::High)]);
     let transactions = [serial::Transaction::write_many(b"some data AT AT\r\n")];
-    let serial = serial::Mock::new(&transactions);
-    let mut hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     hc12.write_buffer(b"some data AT AT\r\n").unwrap();
     let (mut serial, mut set_pin, _) = hc12.release();
     serial.done();
     set_pin.done();
 }
 
+#[test]
+fn send_packet_too_long() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::High)]);
+    let packet = [b'x'; FU4_MAX_PACKET_LEN];
+    let transactions = [serial::Transaction::write_many(packet)];
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    hc12.set_known_parameters(Parameters {
+        baud_rate: BaudRate::Bps1200,
+        mode: Mode::Fu4,
+        ..Default::default()
+    });
+    assert!(matches!(
+        hc12.write_buffer(&[b'x'; FU4_MAX_PACKET_LEN + 1]),
+        Err(crate::Error::PacketTooLong)
+    ));
+    hc12.write_buffer(&packet).unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
 #[test]
 fn receive_buffer() {
     let delay = MockNoop;
     let set_pin = pin::Mock::new(&[pin::Transaction::set(State::High)]);
     let transactions = [serial::Transaction::read_many(b"some data AT AT\r\n")];
-    let serial = serial::Mock::new(&transactions);
-    let mut hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut buffer = [0u8; 32];
     hc12.read_buffer(&mut buffer[..17]).unwrap();
     assert_eq!(&buffer[..17], &b"some data AT AT\r\n"[..]);
@@ -67,6 +189,9 @@ fn usage_from_readme() {
         pin::Transaction::set(State::High),
     ]);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT\r\n"),
         serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+V\r\n"),
@@ -75,16 +200,15 @@ fn usage_from_readme() {
         serial::Transaction::read_many(b"OK+B115200\r\nOK+RC042\r\nOK+RP:-1dBm\r\nOK+FU2\r\n"),
         serial::Transaction::write_many(b"some data AT AT\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
 
     let mut buffer = [0u8; 64];
 
-    let ok = hc12.is_ok();
-    assert!(ok);
+    hc12.is_ok().unwrap();
 
-    let version = hc12.get_version(&mut buffer);
+    let version = hc12.get_version(&mut buffer).unwrap();
     assert_eq!(version, b"VERSION-42\r\n");
 
     let params = hc12.get_parameters().unwrap();
@@ -94,6 +218,7 @@ fn usage_from_readme() {
             channel: Channel::new(42).unwrap(),
             power: TransmissionPower::new(1).unwrap(),
             mode: Mode::Fu2,
+            serial_format: SerialFormat::default(),
         },
         params
     );
@@ -117,14 +242,17 @@ fn get_version() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+V\r\n"),
         serial::Transaction::read_many(b"HC-12_VFAKE\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
     let mut buffer = [0u8; 64];
-    let result = hc12.get_version(&mut buffer);
+    let result = hc12.get_version(&mut buffer).unwrap();
     assert_eq!(result, b"HC-12_VFAKE\r\n");
     let hc12 = hc12.into_normal_mode().debugless_unwrap();
     let (mut serial, mut set_pin, _) = hc12.release();
@@ -142,11 +270,14 @@ fn get_parameters() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+RX\r\n"),
         serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
     let params = hc12.get_parameters().unwrap();
     let hc12 = hc12.into_normal_mode().debugless_unwrap();
@@ -158,6 +289,7 @@ fn get_parameters() {
         channel: Channel::new(1).unwrap(),
         power: TransmissionPower::Eight,
         mode: Mode::Fu3,
+        serial_format: SerialFormat::default(),
     };
     assert_eq!(expected, params);
 }
@@ -172,11 +304,14 @@ fn get_more_parameters() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+RX\r\n"),
         serial::Transaction::read_many(b"OK+B115200\r\nOK+RC101\r\nOK+RP:-1dBm\r\nOK+FU1\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
     let params = hc12.get_parameters().unwrap();
     let hc12 = hc12.into_normal_mode().debugless_unwrap();
@@ -188,6 +323,7 @@ fn get_more_parameters() {
         channel: Channel::new(101).unwrap(),
         power: TransmissionPower::new(1).unwrap(),
         mode: Mode::Fu1,
+        serial_format: SerialFormat::default(),
     };
     assert_eq!(expected, params);
 }
@@ -202,14 +338,16 @@ fn reset_to_default() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+DEFAULT\r\n"),
         serial::Transaction::read_many(b"OK+DEFAULT\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
-    let result = hc12.reset_settings();
-    assert!(result);
+    hc12.reset_settings().unwrap();
     let hc12 = hc12.into_normal_mode().debugless_unwrap();
     let (mut serial, mut set_pin, _) = hc12.release();
     serial.done();
@@ -228,11 +366,17 @@ fn go_to_sleep_and_wake_up() {
     ];
     let set_pin = pin::Mock::new(&pin_transactions);
     let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
         serial::Transaction::write_many(b"AT+SLEEP\r\n"),
         serial::Transaction::read_many(b"OK+SLEEP\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
     ];
-    let serial = serial::Mock::new(&transactions);
-    let hc12 = Hc12::new(serial, set_pin, delay);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
     let hc12 = hc12.into_configuration_mode().debugless_unwrap();
     let result = hc12.into_sleeping_mode();
     let hc12 = result.debugless_unwrap();
@@ -243,3 +387,1126 @@ fn go_to_sleep_and_wake_up() {
     serial.done();
     set_pin.done();
 }
+
+#[test]
+fn set_parameters() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+B9600\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\n"),
+        serial::Transaction::write_many(b"AT+C021\r\n"),
+        serial::Transaction::read_many(b"OK+C021\r\n"),
+        serial::Transaction::write_many(b"AT+FU3\r\n"),
+        serial::Transaction::read_many(b"OK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+P8\r\n"),
+        serial::Transaction::read_many(b"OK+P8\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.set_baud_rate(BaudRate::Bps9600).unwrap();
+    hc12.set_channel(Channel::new(21).unwrap()).unwrap();
+    hc12.set_mode(Mode::Fu3).unwrap();
+    hc12.set_power(TransmissionPower::Eight).unwrap();
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn set_parameter_mismatch() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+C021\r\n"),
+        serial::Transaction::read_many(b"OK+C022\r\n"),
+        serial::Transaction::write_many(b"AT+P8\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert!(matches!(
+        hc12.set_channel(Channel::new(21).unwrap()),
+        Err(crate::Error::Mismatch)
+    ));
+    match hc12.set_power(TransmissionPower::Eight) {
+        Err(crate::Error::UnexpectedResponse(response)) => {
+            assert_eq!(response.as_bytes(), b"ERROR\r\n")
+        }
+        _ => panic!("expected unexpected response"),
+    }
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn apply_parameters() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+C004\r\n"),
+        serial::Transaction::read_many(b"OK+C004\r\n"),
+        serial::Transaction::write_many(b"AT+B2400\r\n"),
+        serial::Transaction::read_many(b"OK+B2400\r\n"),
+        // Serial port follows to 2400 bps
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+FU2\r\n"),
+        serial::Transaction::read_many(b"OK+FU2\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B2400\r\nOK+RC004\r\nOK+RP:+20dBm\r\nOK+FU2\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let target = Parameters {
+        baud_rate: BaudRate::Bps2400,
+        channel: Channel::new(4).unwrap(),
+        power: TransmissionPower::Eight,
+        mode: Mode::Fu2,
+        serial_format: SerialFormat::default(),
+    };
+    hc12.apply(&target).unwrap();
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    assert_eq!(serial.baud_rates, [BaudRate::Bps2400]);
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn apply_parameters_rollback() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+P4\r\n"),
+        serial::Transaction::read_many(b"OK+P4\r\n"),
+        serial::Transaction::write_many(b"AT+FU1\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+        serial::Transaction::write_many(b"AT+FU3\r\n"),
+        serial::Transaction::read_many(b"OK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+P8\r\n"),
+        serial::Transaction::read_many(b"OK+P8\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let target = Parameters {
+        power: TransmissionPower::Four,
+        mode: Mode::Fu1,
+        ..Default::default()
+    };
+    let report = hc12.apply(&target).unwrap_err();
+    assert_eq!(report.attempted(), &[Setting::Power, Setting::Mode]);
+    assert_eq!(report.failed, Some(Setting::Mode));
+    assert!(matches!(report.error, crate::Error::UnexpectedResponse(_)));
+    assert_eq!(report.previous, Some(Parameters::default()));
+    assert!(report.restored);
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn apply_read_back_error() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+C002\r\n"),
+        serial::Transaction::read_many(b"OK+C002\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+    ];
+    transactions.extend(silence(1));
+    transactions.extend([
+        serial::Transaction::write_many(b"AT+C001\r\n"),
+        serial::Transaction::read_many(b"OK+C001\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ]);
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.set_timeout_ms(1);
+    let target = Parameters {
+        channel: Channel::new(2).unwrap(),
+        ..Default::default()
+    };
+    let report = hc12.apply(&target).unwrap_err();
+    assert_eq!(report.failed, None);
+    assert!(matches!(report.error, crate::Error::NoResponse));
+    assert_eq!(report.previous, Some(Parameters::default()));
+    assert!(report.restored);
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn apply_previous_unknown() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+    ];
+    transactions.extend(silence(1));
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.set_timeout_ms(1);
+    let report = hc12.apply(&Parameters::default()).unwrap_err();
+    assert!(matches!(report.error, crate::Error::NoResponse));
+    assert_eq!(report.previous, None);
+    assert!(report.attempted().is_empty());
+    assert!(!report.restored);
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn apply_invalid_parameters() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let target = Parameters {
+        mode: Mode::Fu2,
+        ..Default::default()
+    };
+    let report = hc12.apply(&target).unwrap_err();
+    assert!(report.attempted().is_empty());
+    assert!(matches!(report.error, crate::Error::InvalidBaudRate));
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn unexpected_responses() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"ERR\n"),
+        serial::Transaction::write_many(b"AT+DEFAULT\r\n"),
+        serial::Transaction::read_many(b"OK+DEFAULX\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+X9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC201\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    match hc12.is_ok() {
+        Err(crate::Error::UnexpectedResponse(response)) => {
+            assert_eq!(response.as_bytes(), b"ERR\n")
+        }
+        _ => panic!("expected unexpected response"),
+    }
+    match hc12.reset_settings() {
+        Err(crate::Error::UnexpectedResponse(response)) => {
+            assert_eq!(response.as_bytes(), b"OK+DEFAULX\r\n")
+        }
+        _ => panic!("expected unexpected response"),
+    }
+    assert!(matches!(
+        hc12.get_parameters(),
+        Err(crate::Error::Parse(ResponseError::UnknownIdentifier))
+    ));
+    assert!(matches!(
+        hc12.get_parameters(),
+        Err(crate::Error::Parse(ResponseError::OutOfRange(201)))
+    ));
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn serial_error() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::High)]);
+    let transactions = [
+        serial::Transaction::write_many(b"data"),
+        serial::Transaction::read_error(nb::Error::Other(ErrorKind::Overrun)),
+    ];
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    hc12.write_buffer(b"data").unwrap();
+    let mut buffer = [0u8; 1];
+    assert!(matches!(
+        hc12.read_buffer(&mut buffer),
+        Err(crate::Error::Read(SerialError(ErrorKind::Overrun)))
+    ));
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn command_timeout() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\n"),
+    ];
+    // Half of the timeout passes before the second line, the rest after it.
+    transactions.extend(silence(1).take(5));
+    transactions.push(serial::Transaction::read_many(b"OK+RC001\r\n"));
+    transactions.extend(silence(1).skip(5));
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert_eq!(hc12.timeout_ms(), DEFAULT_TIMEOUT_MS);
+    hc12.set_timeout_ms(1);
+    assert!(matches!(hc12.get_parameters(), Err(crate::Error::Timeout)));
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    assert_eq!(hc12.timeout_ms(), 1);
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn receive_buffer_timeout() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::High)]);
+    let mut transactions = vec![serial::Transaction::read_many(b"some")];
+    transactions.extend(silence(0));
+    transactions.push(serial::Transaction::read_many(b" data"));
+    transactions.extend(silence(2));
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut buffer = [0u8; 32];
+    let count = hc12.read_buffer_timeout(&mut buffer, 0).unwrap();
+    assert_eq!(&buffer[..count], b"some");
+    let count = hc12.read_buffer_timeout(&mut buffer, 2).unwrap();
+    assert_eq!(&buffer[..count], b" data");
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn failed_sleep_returns_driver_and_cause() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+SLEEP\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+        serial::Transaction::write_many(b"AT+SLEEP\r\n"),
+    ];
+    transactions.extend(silence(1));
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let failed = hc12.into_sleeping_mode().debugless_unwrap_err();
+    match failed.error {
+        crate::Error::UnexpectedResponse(response) => {
+            assert_eq!(response.as_bytes(), b"ERROR\r\n")
+        }
+        _ => panic!("expected unexpected response"),
+    }
+    let mut hc12 = failed.hc12;
+    hc12.set_timeout_ms(1);
+    let failed = hc12.into_sleeping_mode().debugless_unwrap_err();
+    assert!(matches!(failed.error, crate::Error::NoResponse));
+    let hc12 = failed.hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn failed_pin_keeps_driver() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low).with_error(embedded_hal_mock::eh1::MockError::Io(
+            std::io::ErrorKind::NotConnected,
+        )),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let failed = hc12.into_configuration_mode().debugless_unwrap_err();
+    assert!(matches!(failed.error, crate::Error::Pin(_)));
+    let hc12 = failed.hc12.into_configuration_mode().debugless_unwrap();
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn probe_baud_rate() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut transactions = vec![
+        // 1200: garbage
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"\x00\xfe\r\n"),
+        // 2400: silence
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ];
+    transactions.extend(silence(u32::from(DEFAULT_TIMEOUT_MS)));
+    transactions.extend([
+        // 4800: stale input is discarded, then Hc12 answers
+        serial::Transaction::read_many(b"\xfe\xfe"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let serial = Serial::new(&transactions);
+    let (hc12, rate) = Hc12::probe(serial, set_pin, delay).unwrap();
+    assert_eq!(rate, BaudRate::Bps4800);
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    assert_eq!(
+        serial.baud_rates,
+        [BaudRate::Bps1200, BaudRate::Bps2400, BaudRate::Bps4800]
+    );
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn probe_without_answer() {
+    let delay = MockNoop;
+    let mut set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut transactions = Vec::new();
+    for _ in BaudRate::ALL {
+        transactions.push(serial::Transaction::read_error(nb::Error::WouldBlock));
+        transactions.push(serial::Transaction::write_many(b"AT\r\n"));
+        transactions.extend(silence(u32::from(DEFAULT_TIMEOUT_MS)));
+    }
+    let mut serial = Serial::new(&transactions);
+    let result = Hc12::probe(&mut serial, &mut set_pin, delay);
+    assert!(matches!(result, Err(crate::Error::NoResponse)));
+    assert_eq!(serial.baud_rates, BaudRate::ALL);
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn recover_with_power_pin() {
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::delay_ms(500),
+        DelayTransaction::delay_ms(250),
+        DelayTransaction::delay_ms(20),
+    ]);
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut power_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let timing = Timing {
+        power_off_ms: 500,
+        power_on_ms: 250,
+        baud_rate_change_ms: 20,
+        ..Timing::default()
+    };
+    let hc12 = Hc12::recover(
+        serial,
+        set_pin,
+        &mut delay,
+        &mut power_pin,
+        timing,
+        Recovery::Keep,
+    )
+    .debugless_unwrap();
+    assert_eq!(*hc12.timing(), timing);
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    assert_eq!(serial.baud_rates, [BaudRate::Bps9600]);
+    serial.done();
+    set_pin.done();
+    power_pin.done();
+    delay.done();
+}
+
+#[test]
+fn recover_power_pin_error() {
+    let delay = MockNoop;
+    let mut set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut power_pin = pin::Mock::new(&[pin::Transaction::set(State::Low).with_error(
+        embedded_hal_mock::eh1::MockError::Io(std::io::ErrorKind::Other),
+    )]);
+    let mut serial = Serial::new(&[]);
+    let result = Hc12::recover(
+        &mut serial,
+        &mut set_pin,
+        delay,
+        &mut power_pin,
+        Timing::default(),
+        Recovery::Keep,
+    );
+    assert!(matches!(
+        result,
+        Err(super::recover::RecoverError::Power(_))
+    ));
+    serial.done();
+    set_pin.done();
+    power_pin.done();
+}
+
+#[test]
+fn change_baud_rate() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+B115200\r\n"),
+        serial::Transaction::read_many(b"OK+B115200\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let (mut hc12, _) = Hc12::probe(serial, set_pin, delay).unwrap();
+    hc12.change_baud_rate(BaudRate::Bps115200).unwrap();
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    assert_eq!(serial.baud_rates, [BaudRate::Bps1200, BaudRate::Bps115200]);
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn change_baud_rate_falls_back() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B1200\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+B19200\r\n"),
+        serial::Transaction::read_many(b"OK+B19200\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ];
+    transactions.extend(silence(1));
+    // Hc12 switched, so it does not answer at the previous rate either
+    transactions.extend([
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ]);
+    transactions.extend(silence(1));
+    transactions.extend([
+        serial::Transaction::write_many(b"AT+B1200\r\n"),
+        serial::Transaction::read_many(b"OK+B1200\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let serial = Serial::new(&transactions);
+    let (mut hc12, _) = Hc12::probe(serial, set_pin, delay).unwrap();
+    hc12.set_timeout_ms(1);
+    let err = hc12.change_baud_rate(BaudRate::Bps19200).unwrap_err();
+    assert!(matches!(err, crate::Error::NoResponse));
+    assert_eq!(hc12.known_parameters().baud_rate, BaudRate::Bps1200);
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    assert_eq!(
+        serial.baud_rates,
+        [
+            BaudRate::Bps1200,
+            BaudRate::Bps19200,
+            BaudRate::Bps1200,
+            BaudRate::Bps19200,
+            BaudRate::Bps1200
+        ]
+    );
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn change_baud_rate_fallback_fails() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B1200\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+B19200\r\n"),
+        serial::Transaction::read_many(b"OK+B19200\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ];
+    transactions.extend(silence(1));
+    transactions.extend([
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ]);
+    transactions.extend(silence(1));
+    transactions.extend([
+        serial::Transaction::write_many(b"AT+B1200\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+    ]);
+    transactions.extend(silence(1));
+    let serial = Serial::new(&transactions);
+    let (mut hc12, _) = Hc12::probe(serial, set_pin, delay).unwrap();
+    hc12.set_timeout_ms(1);
+    let err = hc12.change_baud_rate(BaudRate::Bps19200).unwrap_err();
+    // The failed fallback is reported, not the first error
+    assert!(matches!(err, crate::Error::UnexpectedResponse(_)));
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn change_baud_rate_respects_mode() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[pin::Transaction::set(State::Low)]);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B1200\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU2\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let (mut hc12, _) = Hc12::probe(serial, set_pin, delay).unwrap();
+    let err = hc12.change_baud_rate(BaudRate::Bps9600).unwrap_err();
+    assert!(matches!(err, crate::Error::InvalidBaudRate));
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    assert_eq!(serial.baud_rates, [BaudRate::Bps1200]);
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn serial_format() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+U8E1\r\n"),
+        serial::Transaction::read_many(b"OK+U8E1\r\n"),
+        // Without the firmware version, the serial format is not queried
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+V\r\n"),
+        serial::Transaction::read_many(b"www.hc01.com HC-12_V2.6\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+RU\r\n"),
+        serial::Transaction::read_many(b"OK+U8O1\r\n"),
+        // Firmware which rejects the serial format query
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+RU\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+        // Firmware which does not answer the serial format query
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+        serial::Transaction::write_many(b"AT+RU\r\n"),
+    ];
+    transactions.extend(silence(1));
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.set_timeout_ms(1);
+    let even = SerialFormat::new(Parity::Even, StopBits::One);
+    hc12.set_serial_format(even).unwrap();
+    assert_eq!(hc12.get_parameters().unwrap().serial_format, even);
+    hc12.get_firmware_version().unwrap();
+    let odd = SerialFormat::new(Parity::Odd, StopBits::One);
+    assert_eq!(hc12.get_parameters().unwrap().serial_format, odd);
+    assert_eq!(hc12.get_parameters().unwrap().serial_format, odd);
+    assert_eq!(hc12.get_parameters().unwrap().serial_format, odd);
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn firmware_capabilities() {
+    let delay = MockNoop;
+    let pin_transactions = [
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ];
+    let set_pin = pin::Mock::new(&pin_transactions);
+    let transactions = [
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+V\r\n"),
+        serial::Transaction::read_many(b"www.example.com XY-12_V1.1\r\n"),
+        serial::Transaction::write_many(b"AT+RX\r\n"),
+        serial::Transaction::read_many(b"OK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\n"),
+    ];
+    let serial = Serial::new(&transactions);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert!(hc12.capabilities().fu4);
+    let version = hc12.get_firmware_version().unwrap();
+    assert_eq!(version.vendor(), b"www.example.com");
+    assert_eq!((version.major, version.minor), (1, 1));
+    assert!(!hc12.capabilities().fu4);
+    assert!(matches!(
+        hc12.set_mode(Mode::Fu4),
+        Err(crate::Error::Unsupported)
+    ));
+    assert!(matches!(
+        hc12.set_serial_format(SerialFormat::default()),
+        Err(crate::Error::Unsupported)
+    ));
+    // The serial format is not queried from firmware which cannot set it
+    assert_eq!(hc12.get_parameters().unwrap(), Parameters::default());
+    hc12.set_capabilities(Capabilities::ALL);
+    assert!(hc12.capabilities().fu4);
+    hc12.set_capabilities(Capabilities {
+        max_power: TransmissionPower::Five,
+        ..Capabilities::ALL
+    });
+    assert!(matches!(
+        hc12.set_power(TransmissionPower::Six),
+        Err(crate::Error::Unsupported)
+    ));
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn custom_timing() {
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::delay_ms(80),
+        DelayTransaction::delay_ms(150),
+        DelayTransaction::delay_ms(300),
+    ]);
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let mut hc12 = Hc12::new(serial, set_pin, &mut delay).unwrap();
+    assert_eq!(*hc12.timing(), Timing::default());
+    hc12.set_timing(Timing {
+        enter_configuration_ms: 150,
+        exit_configuration_ms: 300,
+        ..Timing::default()
+    });
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    assert_eq!(hc12.timing().enter_configuration_ms, 150);
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+    delay.done();
+}
+
+#[test]
+fn timing_after_failed_entry_and_sleep() {
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::delay_ms(80),
+        DelayTransaction::delay_ms(150),
+        DelayTransaction::delay_ms(300),
+        DelayTransaction::delay_ms(150),
+        DelayTransaction::delay_ms(300),
+    ]);
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"ERROR\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+SLEEP\r\n"),
+        serial::Transaction::read_many(b"OK+SLEEP\r\n"),
+    ]);
+    let mut hc12 = Hc12::new(serial, set_pin, &mut delay).unwrap();
+    hc12.set_timing(Timing {
+        enter_configuration_ms: 150,
+        exit_configuration_ms: 300,
+        confirm_attempts: 1,
+        ..Timing::default()
+    });
+    let TransitionError { hc12, error } = hc12.into_configuration_mode().debugless_unwrap_err();
+    assert!(matches!(error, crate::Error::UnexpectedResponse(_)));
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let hc12 = hc12.into_sleeping_mode().debugless_unwrap();
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+    delay.done();
+}
+
+#[test]
+fn confirm_retries_partial_answer() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ]);
+    let mut transactions = vec![
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        // Hc12 stops in the middle of the line
+        serial::Transaction::read_many(b"O"),
+    ];
+    transactions.extend(silence(1));
+    transactions.extend([
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    hc12.set_timeout_ms(1);
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn confirm_configuration_mode() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+        // Hc12 never answers, so SET is released again
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+    ]);
+    let mut transactions = vec![
+        // Received radio data is discarded
+        serial::Transaction::read_many(b"data"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        // Not ready yet
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"\xfe\r\n"),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ];
+    for _ in 0..2 {
+        transactions.push(serial::Transaction::read_error(nb::Error::WouldBlock));
+        transactions.push(serial::Transaction::write_many(b"AT\r\n"));
+        transactions.extend(silence(1));
+    }
+    let serial = Serial::new(&transactions);
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    hc12.set_timeout_ms(1);
+    hc12.set_timing(Timing {
+        confirm_attempts: 2,
+        ..Timing::default()
+    });
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let TransitionError { hc12, error } = hc12.into_configuration_mode().debugless_unwrap_err();
+    assert!(matches!(error, crate::Error::NoResponse));
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn drain_before_configuration_mode() {
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::delay_ms(80),
+        // The last 60 of 100 bytes at 15000 bps on the air in FU3
+        DelayTransaction::delay_ms(120),
+        DelayTransaction::delay_ms(40),
+        DelayTransaction::delay_ms(80),
+        // 10 bytes at 500 bps on the air in FU4
+        DelayTransaction::delay_ms(1200),
+        DelayTransaction::delay_ms(40),
+    ]);
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::write_many([0x55; 100]),
+        serial::Transaction::flush(),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"0123456789"),
+        serial::Transaction::flush(),
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+    ]);
+    let mut hc12 = Hc12::new(serial, set_pin, &mut delay).unwrap();
+    hc12.write_buffer(&[0x55; 100]).unwrap();
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let mut hc12 = hc12.into_normal_mode().debugless_unwrap();
+    hc12.set_known_parameters(Parameters {
+        mode: Mode::Fu4,
+        baud_rate: BaudRate::Bps1200,
+        ..Parameters::default()
+    });
+    embedded_io::Write::write_all(&mut hc12, b"0123456789").unwrap();
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = hc12;
+    serial.done();
+    set_pin.done();
+    delay.done();
+}
+
+#[test]
+fn any_hc12_keeps_driver() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low).with_error(embedded_hal_mock::eh1::MockError::Io(
+            std::io::ErrorKind::NotConnected,
+        )),
+        pin::Transaction::set(State::Low),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+C021\r\n"),
+        serial::Transaction::read_many(b"OK+C021\r\n"),
+    ]);
+    let hc12 = AnyHc12::new(serial, set_pin, delay).unwrap();
+    let (mut hc12, result) = hc12.into_configuration_mode();
+    assert!(matches!(result, Err(crate::Error::Pin(_))));
+    assert_eq!(hc12.current_mode(), ModeKind::Normal);
+    assert!(matches!(
+        hc12.set_channel(Channel::new(21).unwrap()),
+        Err(crate::Error::WrongMode(ModeKind::Normal))
+    ));
+    let (hc12, result) = hc12.into_configuration_mode();
+    result.unwrap();
+    let (mut hc12, result) = hc12.into_configuration_mode();
+    result.unwrap();
+    hc12.set_channel(Channel::new(21).unwrap()).unwrap();
+    assert_eq!(hc12.known_parameters().channel, Channel::new(21).unwrap());
+    let (mut serial, mut set_pin, _) = hc12.release();
+    serial.done();
+    set_pin.done();
+}
+
+#[test]
+fn with_configuration_exit_fails() {
+    let delay = MockNoop;
+    let set_pin = pin::Mock::new(&[
+        pin::Transaction::set(State::High),
+        pin::Transaction::set(State::Low),
+        pin::Transaction::set(State::High).with_error(embedded_hal_mock::eh1::MockError::Io(
+            std::io::ErrorKind::NotConnected,
+        )),
+    ]);
+    let serial = Serial::new(&[
+        serial::Transaction::read_error(nb::Error::WouldBlock),
+        serial::Transaction::write_many(b"AT\r\n"),
+        serial::Transaction::read_many(b"OK\r\n"),
+        serial::Transaction::write_many(b"AT+C021\r\n"),
+        serial::Transaction::read_many(b"OK+C021\r\n"),
+    ]);
+    let hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    let err = hc12
+        .with_configuration(|hc12| hc12.set_channel(Channel::new(21).unwrap()))
+        .debugless_unwrap_err();
+    let ConfigurationError::Exit { failed, result } = err else {
+        panic!("leaving config mode should have failed");
+    };
+    assert!(result.is_ok());
+    assert!(matches!(failed.error, crate::Error::Pin(_)));
+    let Hc12 {
+        mut serial,
+        mut set_pin,
+        ..
+    } = failed.hc12;
+    serial.done();
+    set_pin.done();
+}
@@ -0,0 +1,67 @@
+//! Settle times of the transitions between the modes of Hc12.
+//!
+//! The datasheet gives 40 ms until Hc12 accepts AT commands after SET was pulled low,
+//! and 80 ms until it is back in transparent mode after SET was released.
+//! It gives no figures for power cycles, baud rate changes or confirming config mode,
+//! so the defaults for those are empirical choices of this driver with some margin.
+//! Slow modules and clones may need more, so all times can be changed.
+
+/// Time in milliseconds from SET going low until Hc12 accepts AT commands, according to the datasheet
+pub const ENTER_CONFIGURATION_MS: u32 = 40;
+
+/// Time in milliseconds from SET going high until Hc12 is in transparent mode, according to the datasheet
+pub const EXIT_CONFIGURATION_MS: u32 = 80;
+
+/// Time in milliseconds power stays off while power-cycling, so Hc12 really shuts down.
+/// Empirical choice, not from the datasheet.
+pub const POWER_OFF_MS: u32 = 200;
+
+/// Time in milliseconds Hc12 needs to start after power is applied.
+/// Empirical choice, not from the datasheet.
+pub const POWER_ON_MS: u32 = 100;
+
+/// Time in milliseconds Hc12 needs to switch its serial port to a new baud rate.
+/// Empirical choice, not from the datasheet, equal to `ENTER_CONFIGURATION_MS`.
+pub const BAUD_RATE_CHANGE_MS: u32 = 40;
+
+/// Number of "AT" queries which confirm config mode.
+/// Empirical choice, not from the datasheet.
+pub const CONFIRM_ATTEMPTS: u8 = 3;
+
+/// Time in milliseconds between "AT" queries while confirming config mode.
+/// Empirical choice, not from the datasheet.
+pub const CONFIRM_INTERVAL_MS: u32 = 10;
+
+/// Waits of Hc12 around mode transitions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timing {
+    /// Time in milliseconds to wait after SET went low, before the first command
+    pub enter_configuration_ms: u32,
+    /// Time in milliseconds to wait after SET went high, before data is sent or received
+    pub exit_configuration_ms: u32,
+    /// Time in milliseconds power stays off while power-cycling
+    pub power_off_ms: u32,
+    /// Time in milliseconds to wait after power was applied, before the first command
+    pub power_on_ms: u32,
+    /// Time in milliseconds to wait after the baud rate changed, before Hc12 is queried again
+    pub baud_rate_change_ms: u32,
+    /// Number of "AT" queries which confirm config mode after `enter_configuration_ms`.
+    /// With 0, Hc12 is assumed to be in config mode after the wait.
+    pub confirm_attempts: u8,
+    /// Time in milliseconds between "AT" queries while confirming config mode
+    pub confirm_interval_ms: u32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            enter_configuration_ms: ENTER_CONFIGURATION_MS,
+            exit_configuration_ms: EXIT_CONFIGURATION_MS,
+            power_off_ms: POWER_OFF_MS,
+            power_on_ms: POWER_ON_MS,
+            baud_rate_change_ms: BAUD_RATE_CHANGE_MS,
+            confirm_attempts: CONFIRM_ATTEMPTS,
+            confirm_interval_ms: CONFIRM_INTERVAL_MS,
+        }
+    }
+}
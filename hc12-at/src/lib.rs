@@ -1,25 +1,125 @@
 //! Hc12 driver
 //! This driver implements normal, config and sleep functionality of the hc12 module.
 
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![deny(unsafe_code)]
 #![deny(missing_docs)]
 
+use core::convert::Infallible;
+use core::fmt;
+
+use settings::response_parser::ResponseError;
+
 /// Hc12 settings
 pub mod settings;
 
 /// Hc12 driver
 pub mod hc12;
 
+/// Adapters for embedded-hal 0.2
+#[cfg(feature = "embedded-hal-02")]
+pub mod compat;
+
+/// Async Hc12 driver
+#[cfg(feature = "async")]
+pub mod asynch;
+
+/// Virtual Hc12 for tests
+#[cfg(feature = "std")]
+pub mod sim;
+
+/// Serial ports of a host computer
+#[cfg(feature = "serialport")]
+pub mod serial_port;
+
 /// Crate error
+///
+/// The driver reports errors of the serial port and the SET pin as `SerialErr` and `PinErr`.
+/// Where no hardware is involved, like validating parameters, both are `Infallible`.
 #[derive(Debug)]
-pub enum Error {
-    /// Read error
-    Read,
-    /// Write error
-    Write,
+pub enum Error<SerialErr = Infallible, PinErr = Infallible> {
+    /// Reading from the serial port failed
+    Read(SerialErr),
+    /// Writing to the serial port failed
+    Write(SerialErr),
+    /// Setting the SET pin failed
+    Pin(PinErr),
+    /// Changing the baud rate of the serial port failed
+    Reconfigure(SerialErr),
+    /// Response could not be parsed
+    Parse(ResponseError),
+    /// Hc12 sent a response other than the expected one
+    UnexpectedResponse(RawResponse),
+    /// Response acknowledged a different value than requested
+    Mismatch,
+    /// Hc12 did not send a single byte in time
+    NoResponse,
+    /// Hc12 did not complete its response in time
+    Timeout,
     /// Invalid baud rate
     InvalidBaudRate,
     /// Invalid channel error
     InvalidChannel,
+    /// Packet exceeds the maximum length of the mode
+    PacketTooLong,
+    /// Firmware of Hc12 does not implement the command
+    Unsupported,
+    /// Hc12 is not in the mode the call needs; holds the mode it is in
+    WrongMode(hc12::ModeKind),
+}
+
+impl Error {
+    /// Convert an error without hardware involvement into a driver error
+    pub(crate) fn widen<SerialErr, PinErr>(self) -> Error<SerialErr, PinErr> {
+        match self {
+            Error::Read(e) | Error::Write(e) | Error::Reconfigure(e) => match e {},
+            Error::Pin(e) => match e {},
+            Error::Parse(e) => Error::Parse(e),
+            Error::UnexpectedResponse(r) => Error::UnexpectedResponse(r),
+            Error::Mismatch => Error::Mismatch,
+            Error::NoResponse => Error::NoResponse,
+            Error::Timeout => Error::Timeout,
+            Error::InvalidBaudRate => Error::InvalidBaudRate,
+            Error::InvalidChannel => Error::InvalidChannel,
+            Error::PacketTooLong => Error::PacketTooLong,
+            Error::Unsupported => Error::Unsupported,
+            Error::WrongMode(mode) => Error::WrongMode(mode),
+        }
+    }
+}
+
+impl<SerialErr, PinErr> From<ResponseError> for Error<SerialErr, PinErr> {
+    fn from(e: ResponseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+/// Bytes of a response received from Hc12, truncated to 32 bytes
+#[derive(Clone, PartialEq, Eq)]
+pub struct RawResponse {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl RawResponse {
+    /// Copy a response, truncating it if necessary
+    pub fn new(response: &[u8]) -> Self {
+        let mut bytes = [0u8; 32];
+        let len = response.len().min(bytes.len());
+        bytes[..len].copy_from_slice(&response[..len]);
+        Self { bytes, len }
+    }
+
+    /// Received bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Debug for RawResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawResponse")
+            .field(&self.as_bytes())
+            .finish()
+    }
 }
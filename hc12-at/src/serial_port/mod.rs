@@ -0,0 +1,165 @@
+//! Serial port of a host computer, with the SET pin on its RTS or DTR line.
+//!
+//! PCs have no GPIO, so SET is wired to a modem control line of the USB-serial adapter.
+//! `SharedSerial` is the data path for the driver, and `ModemLinePin` drives SET through the same port.
+//! Both hold the port behind a mutex, and neither holds the lock while waiting for data.
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+use num_traits::ToPrimitive;
+use serialport::SerialPort;
+
+use crate::hc12::baud_rate::ReconfigureBaudRate;
+use crate::settings::parameter::baudrate::BaudRate;
+
+#[cfg(test)]
+mod test;
+
+/// Time between checks for received bytes while a read waits
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Modem control line of a serial port
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ControlLine {
+    /// Request To Send
+    Rts,
+    /// Data Terminal Ready
+    Dtr,
+}
+
+/// Lock the port, even if another handle panicked while holding it
+fn lock<T>(port: &Mutex<T>) -> MutexGuard<'_, T> {
+    port.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Serial port whose handle is shared with a `ModemLinePin`
+#[derive(Debug)]
+pub struct SharedSerial<T> {
+    port: Arc<Mutex<T>>,
+}
+
+impl<T: SerialPort> SharedSerial<T> {
+    /// Share the port
+    pub fn new(port: T) -> Self {
+        Self {
+            port: Arc::new(Mutex::new(port)),
+        }
+    }
+
+    /// SET pin on a control line of this port.
+    ///
+    /// TTL adapters drive an asserted line low, so `set_low` asserts the line.
+    /// Set `inverted` if SET is connected through an inverting stage, so `set_low` releases the line.
+    pub fn set_pin(&self, line: ControlLine, inverted: bool) -> ModemLinePin<T> {
+        ModemLinePin {
+            port: self.port.clone(),
+            line,
+            inverted,
+        }
+    }
+
+    /// Share the port and take the SET pin on one of its control lines, see `SharedSerial::set_pin`
+    pub fn split(port: T, line: ControlLine, inverted: bool) -> (Self, ModemLinePin<T>) {
+        let serial = Self::new(port);
+        let set_pin = serial.set_pin(line, inverted);
+        (serial, set_pin)
+    }
+}
+
+impl<T: SerialPort> ErrorType for SharedSerial<T> {
+    type Error = io::Error;
+}
+
+impl<T: SerialPort> ReadReady for SharedSerial<T> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(lock(&self.port).bytes_to_read()? > 0)
+    }
+}
+
+impl<T: SerialPort> Read for SharedSerial<T> {
+    /// Blocks until at least one byte arrived.
+    /// The port is polled for received bytes and read only once some arrived,
+    /// so the lock is not held while waiting and the SET pin stays usable.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            {
+                let mut port = lock(&self.port);
+                if port.bytes_to_read()? > 0 {
+                    return port.read(buf);
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl<T: SerialPort> Write for SharedSerial<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        lock(&self.port).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        lock(&self.port).flush()
+    }
+}
+
+impl<T: SerialPort> ReconfigureBaudRate for SharedSerial<T> {
+    fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Self::Error> {
+        let baud = rate.to_u32().unwrap_or(9600);
+        Ok(lock(&self.port).set_baud_rate(baud)?)
+    }
+}
+
+/// Error of a `ModemLinePin`
+#[derive(Debug)]
+pub struct ModemLineError(pub serialport::Error);
+
+impl digital::Error for ModemLineError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+/// SET pin on the RTS or DTR line of a serial port
+#[derive(Debug)]
+pub struct ModemLinePin<T> {
+    port: Arc<Mutex<T>>,
+    line: ControlLine,
+    inverted: bool,
+}
+
+impl<T: SerialPort> ModemLinePin<T> {
+    /// Control line which drives SET
+    pub fn line(&self) -> ControlLine {
+        self.line
+    }
+
+    /// Assert or release the control line
+    fn write(&mut self, asserted: bool) -> Result<(), ModemLineError> {
+        let mut port = lock(&self.port);
+        match self.line {
+            ControlLine::Rts => port.write_request_to_send(asserted),
+            ControlLine::Dtr => port.write_data_terminal_ready(asserted),
+        }
+        .map_err(ModemLineError)
+    }
+}
+
+impl<T: SerialPort> digital::ErrorType for ModemLinePin<T> {
+    type Error = ModemLineError;
+}
+
+impl<T: SerialPort> OutputPin for ModemLinePin<T> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.write(!self.inverted)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.write(self.inverted)
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, StopBits};
+
+use crate::hc12::Hc12;
+
+use super::*;
+
+use debugless_unwrap::{DebuglessUnwrap, DebuglessUnwrapErr};
+use embedded_hal_mock::eh1::delay::NoopDelay as MockNoop;
+
+/// What happened at a `FakePort`
+#[derive(Debug, Default)]
+struct Lines {
+    rts: bool,
+    dtr: bool,
+    baud_rate: u32,
+    /// Bytes waiting to be read
+    rx: VecDeque<u8>,
+    /// Bytes written
+    tx: Vec<u8>,
+    /// Whether setting the control lines fails
+    broken: bool,
+    /// How long a read waits for data before it times out
+    timeout: Duration,
+}
+
+/// Serial port which records its control lines, as pseudo-terminals have none
+#[derive(Debug, Clone, Default)]
+struct FakePort(Arc<Mutex<Lines>>);
+
+impl FakePort {
+    fn lines(&self) -> MutexGuard<'_, Lines> {
+        self.0.lock().unwrap()
+    }
+
+    fn write_line(&mut self, level: bool, rts: bool) -> serialport::Result<()> {
+        let mut lines = self.lines();
+        if lines.broken {
+            return Err(serialport::Error::new(
+                serialport::ErrorKind::Unknown,
+                "not a typewriter",
+            ));
+        }
+        if rts {
+            lines.rts = level;
+        } else {
+            lines.dtr = level;
+        }
+        Ok(())
+    }
+}
+
+impl io::Read for FakePort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut lines = self.lines();
+        match lines.rx.pop_front() {
+            Some(byte) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            None => {
+                let timeout = lines.timeout;
+                drop(lines);
+                std::thread::sleep(timeout);
+                Err(io::ErrorKind::TimedOut.into())
+            }
+        }
+    }
+}
+
+impl io::Write for FakePort {
+    /// Answers "AT" like Hc12 in config mode
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = self.lines();
+        lines.tx.extend_from_slice(buf);
+        if buf == b"AT\r\n" {
+            lines.rx.extend(b"OK\r\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for FakePort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(self.lines().baud_rate)
+    }
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+    fn timeout(&self) -> Duration {
+        Duration::ZERO
+    }
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.lines().baud_rate = baud_rate;
+        Ok(())
+    }
+    fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_parity(&mut self, _: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_timeout(&mut self, _: Duration) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.write_line(level, true)
+    }
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.write_line(level, false)
+    }
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(self.lines().rx.len() as u32)
+    }
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+    fn clear(&self, _: ClearBuffer) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(self.clone()))
+    }
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn set_pin_levels() {
+    let port = FakePort::default();
+    let serial = SharedSerial::new(port.clone());
+
+    let mut rts = serial.set_pin(ControlLine::Rts, false);
+    assert_eq!(rts.line(), ControlLine::Rts);
+    rts.set_low().unwrap();
+    assert!(port.lines().rts);
+    rts.set_high().unwrap();
+    assert!(!port.lines().rts);
+
+    let mut dtr = serial.set_pin(ControlLine::Dtr, true);
+    dtr.set_high().unwrap();
+    assert!(port.lines().dtr);
+    dtr.set_low().unwrap();
+    assert!(!port.lines().dtr);
+    assert!(!port.lines().rts);
+}
+
+#[test]
+fn drive_hc12() {
+    let port = FakePort::default();
+    // Received radio data is discarded before config mode is confirmed
+    port.lines().rx.extend(b"data");
+    let (serial, set_pin) = SharedSerial::split(port.clone(), ControlLine::Rts, false);
+
+    let hc12 = Hc12::new(serial, set_pin, MockNoop).unwrap();
+    assert!(!port.lines().rts);
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert!(port.lines().rts);
+    hc12.is_ok().unwrap();
+    assert_eq!(port.lines().tx, b"AT\r\nAT\r\n");
+    assert!(port.lines().rx.is_empty());
+
+    let _hc12 = hc12.into_normal_mode().debugless_unwrap();
+    assert!(!port.lines().rts);
+}
+
+#[test]
+fn set_pin_while_reading() {
+    let port = FakePort::default();
+    port.lines().timeout = Duration::from_secs(1);
+    let (mut serial, mut set_pin) = SharedSerial::split(port.clone(), ControlLine::Rts, false);
+    let reader = std::thread::spawn(move || {
+        let mut buffer = [0u8; 1];
+        serial.read(&mut buffer).map(|_| buffer[0])
+    });
+    std::thread::sleep(Duration::from_millis(20));
+
+    // The waiting read does not hold the port
+    let start = std::time::Instant::now();
+    set_pin.set_low().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert!(port.lines().rts);
+
+    port.lines().rx.push_back(b'x');
+    assert_eq!(reader.join().unwrap().unwrap(), b'x');
+}
+
+#[test]
+fn reconfigure_baud_rate() {
+    let port = FakePort::default();
+    let mut serial = SharedSerial::new(port.clone());
+    serial.set_baud_rate(BaudRate::Bps115200).unwrap();
+    assert_eq!(port.lines().baud_rate, 115200);
+}
+
+#[test]
+fn pin_error() {
+    let port = FakePort::default();
+    port.lines().broken = true;
+    let (serial, set_pin) = SharedSerial::split(port, ControlLine::Dtr, false);
+    let err = Hc12::new(serial, set_pin, MockNoop).debugless_unwrap_err();
+    assert!(matches!(err, crate::Error::Pin(ModemLineError(_))));
+}
@@ -1,8 +1,9 @@
-//! Convert a Baudrate, Channel, Mode, or Power, to a command for configuring the Hc12.
+//! Convert a Baudrate, Channel, Mode, Power, or SerialFormat, to a command for configuring the Hc12.
 
 pub(crate) mod baudrate;
 pub(crate) mod channel;
 pub(crate) mod mode;
+pub(crate) mod serial_format;
 pub(crate) mod transmission_power;
 
 #[cfg(test)]
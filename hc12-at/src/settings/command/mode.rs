@@ -4,23 +4,9 @@ use super::MakeCommand;
 
 impl MakeCommand for Mode {
     fn make_command<'a>(&self, buffer: &'a mut [u8; 16]) -> &'a [u8] {
-        match self {
-            Mode::Fu1 => {
-                buffer[0..8].copy_from_slice(b"AT+FU1\r\n");
-                &buffer[..8]
-            }
-            Mode::Fu2 => {
-                buffer[0..8].copy_from_slice(b"AT+FU2\r\n");
-                &buffer[..8]
-            }
-            Mode::Fu3 => {
-                buffer[0..8].copy_from_slice(b"AT+FU3\r\n");
-                &buffer[..8]
-            }
-            Mode::Fu4 => {
-                buffer[0..8].copy_from_slice(b"AT+FU4\r\n");
-                &buffer[..8]
-            }
-        }
+        buffer[..5].copy_from_slice(b"AT+FU");
+        buffer[5] = b'0' + self.number();
+        buffer[6..8].copy_from_slice(b"\r\n");
+        &buffer[..8]
     }
 }
@@ -0,0 +1,29 @@
+use crate::settings::parameter::serial_format::{Parity, SerialFormat, StopBits};
+
+use super::MakeCommand;
+
+impl MakeCommand for SerialFormat {
+    fn make_command<'a>(&self, buffer: &'a mut [u8; 16]) -> &'a [u8] {
+        let format: [u8; 3] = self.into();
+        buffer[..4].copy_from_slice(b"AT+U");
+        buffer[4..7].copy_from_slice(&format);
+        buffer[7..9].copy_from_slice(b"\r\n");
+        &buffer[..9]
+    }
+}
+
+impl From<&SerialFormat> for [u8; 3] {
+    fn from(f: &SerialFormat) -> Self {
+        let parity = match f.parity {
+            Parity::None => b'N',
+            Parity::Odd => b'O',
+            Parity::Even => b'E',
+        };
+        let stop_bits = match f.stop_bits {
+            StopBits::One => b'1',
+            StopBits::Two => b'2',
+            StopBits::OneAndHalf => b'3',
+        };
+        [b'8', parity, stop_bits]
+    }
+}
@@ -1,7 +1,11 @@
 use crate::settings::{
     command::MakeCommand,
     parameter::{
-        baudrate::BaudRate, channel::Channel, mode::Mode, transmission_power::TransmissionPower,
+        baudrate::BaudRate,
+        channel::Channel,
+        mode::Mode,
+        serial_format::{Parity, SerialFormat, StopBits},
+        transmission_power::TransmissionPower,
     },
 };
 
@@ -44,3 +48,14 @@ fn set_power_command() {
         assert_eq!(expected.as_bytes(), c);
     }
 }
+
+#[test]
+fn set_serial_format_command() {
+    let mut buffer = [0u8; 16];
+    let format = SerialFormat::default();
+    assert_eq!(b"AT+U8N1\r\n", format.make_command(&mut buffer));
+    let format = SerialFormat::new(Parity::Odd, StopBits::Two);
+    assert_eq!(b"AT+U8O2\r\n", format.make_command(&mut buffer));
+    let format = SerialFormat::new(Parity::Even, StopBits::OneAndHalf);
+    assert_eq!(b"AT+U8E3\r\n", format.make_command(&mut buffer));
+}
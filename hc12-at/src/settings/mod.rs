@@ -9,3 +9,9 @@ pub mod query;
 
 /// Hc12 response parser
 pub mod response_parser;
+
+/// AT commands and responses
+pub mod protocol;
+
+/// Firmware version and the features it implements
+pub mod version;
@@ -15,7 +15,7 @@ impl TryFrom<u8> for Channel {
 }
 
 /// Communication channel
-#[derive(Debug, ToPrimitive, FromPrimitive, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, ToPrimitive, FromPrimitive, PartialEq, Eq)]
 pub struct Channel(u8);
 
 impl Channel {
@@ -4,6 +4,8 @@ pub mod baudrate;
 pub mod channel;
 /// Operational mode datastructures
 pub mod mode;
+/// Serial data format
+pub mod serial_format;
 /// Transmission power
 pub mod transmission_power;
 
@@ -12,6 +14,7 @@ pub mod parameters;
 
 pub(crate) const OK_QUERY: [u8; 4] = *b"AT\r\n";
 pub(crate) const OK_RESPONSE: [u8; 4] = *b"OK\r\n";
+pub(crate) const ERROR_RESPONSE: [u8; 7] = *b"ERROR\r\n";
 
 pub(crate) const QUERY_PARAMS_COMMAND: [u8; 7] = *b"AT+RX\r\n";
 
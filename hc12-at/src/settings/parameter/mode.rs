@@ -1,18 +1,45 @@
-/// Operational mode
-#[derive(Debug, PartialEq, Eq)]
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+/// Operational mode, numbered as in "FUx"
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum Mode {
     /// Function 1
-    Fu1,
+    Fu1 = 1,
     /// Function 2
-    Fu2,
+    Fu2 = 2,
     /// Function 3
-    Fu3,
+    Fu3 = 3,
     /// Function 4
-    Fu4,
+    Fu4 = 4,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Mode {
     fn default() -> Self {
         Self::Fu3
     }
 }
+
+impl Mode {
+    /// Construct a Mode from its number, if the number is valid
+    pub fn new(n: u8) -> Option<Self> {
+        Mode::from_u8(n)
+    }
+
+    /// Number of the mode, as in "FUx"
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Delay in milliseconds from the serial port of the sender to the air, the upper bound given by the datasheet
+    pub fn transmission_delay_ms(&self) -> u32 {
+        match self {
+            Mode::Fu1 => 25,
+            Mode::Fu2 => 500,
+            Mode::Fu3 => 80,
+            Mode::Fu4 => 1000,
+        }
+    }
+}
@@ -1,9 +1,37 @@
+use core::convert::TryFrom;
+
+use num_traits::ToPrimitive;
+
+use crate::Error;
+
 use super::{
-    baudrate::BaudRate, channel::Channel, mode::Mode, transmission_power::TransmissionPower,
+    baudrate::{AirBaudRate, BaudRate},
+    channel::Channel,
+    mode::Mode,
+    serial_format::SerialFormat,
+    transmission_power::TransmissionPower,
 };
 
+/// Maximum number of bytes in a single packet in FU4 mode
+pub const FU4_MAX_PACKET_LEN: usize = 60;
+
+/// Most bytes Hc12 is assumed to still hold once the serial port is flushed.
+///
+/// Except in FU4, the air baud rate is above the serial baud rate, so the module sends what it gets and holds about one packet.
+/// In FU4, a packet has at most `FU4_MAX_PACKET_LEN` bytes.
+pub const MAX_PENDING_LEN: usize = FU4_MAX_PACKET_LEN;
+
+/// Bits on the air per byte, including start and stop bit
+const AIR_BITS_PER_BYTE: u64 = 10;
+
+/// Time in microseconds to send `len` bytes over the air at `rate`
+pub fn airtime_us(len: usize, rate: AirBaudRate) -> u64 {
+    let bits = len as u64 * AIR_BITS_PER_BYTE * 1_000_000;
+    bits.div_ceil(rate.to_u64().unwrap_or(1))
+}
+
 /// All hc12 parameters
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct Parameters {
     /// Baud rate
     pub baud_rate: BaudRate,
@@ -13,4 +41,123 @@ pub struct Parameters {
     pub power: TransmissionPower,
     /// Operation mode
     pub mode: Mode,
+    /// Serial data format
+    pub serial_format: SerialFormat,
+}
+
+impl Parameters {
+    /// Check that the parameters form a configuration the hc12 accepts
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut check = Parameters {
+            mode: self.mode,
+            ..Default::default()
+        };
+        check.set_baud_rate(self.baud_rate)
+    }
+
+    /// Maximum number of bytes Hc12 transmits in a single packet, if the mode limits it
+    pub fn max_packet_len(&self) -> Option<usize> {
+        match self.mode {
+            Mode::Fu4 => Some(FU4_MAX_PACKET_LEN),
+            Mode::Fu1 | Mode::Fu2 | Mode::Fu3 => None,
+        }
+    }
+
+    /// Estimated time in milliseconds until `len` bytes which reached Hc12 were sent over the air.
+    ///
+    /// The bytes pass the air at the air baud rate, and the mode adds its transmission delay.
+    /// Their time on the serial port is not included, as it is over once the serial port of the host is flushed.
+    pub fn transmission_time_ms(&self, len: usize) -> u32 {
+        if len == 0 {
+            return 0;
+        }
+        let air_us = airtime_us(len, self.get_air_baud_rate());
+        let ms = air_us.div_ceil(1000) + u64::from(self.mode.transmission_delay_ms());
+        u32::try_from(ms).unwrap_or(u32::MAX)
+    }
+
+    /// Check that a packet of `len` bytes can be transmitted in one piece
+    pub fn validate_packet_len(&self, len: usize) -> Result<(), Error> {
+        match self.max_packet_len() {
+            Some(max) if len > max => Err(Error::PacketTooLong),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::settings::parameter::{
+        baudrate::{AirBaudRate, BaudRate},
+        mode::Mode,
+        parameters::{airtime_us, Parameters, FU4_MAX_PACKET_LEN},
+    };
+
+    #[test]
+    fn validate() {
+        assert!(Parameters::default().validate().is_ok());
+        let params = Parameters {
+            mode: Mode::Fu2,
+            baud_rate: BaudRate::Bps4800,
+            ..Default::default()
+        };
+        assert!(params.validate().is_ok());
+        let params = Parameters {
+            mode: Mode::Fu2,
+            baud_rate: BaudRate::Bps9600,
+            ..Default::default()
+        };
+        assert!(params.validate().is_err());
+        let params = Parameters {
+            mode: Mode::Fu4,
+            baud_rate: BaudRate::Bps1200,
+            ..Default::default()
+        };
+        assert!(params.validate().is_ok());
+        let params = Parameters {
+            mode: Mode::Fu4,
+            ..Default::default()
+        };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn packet_len() {
+        let params = Parameters::default();
+        assert_eq!(params.max_packet_len(), None);
+        assert!(params.validate_packet_len(1000).is_ok());
+        let params = Parameters {
+            mode: Mode::Fu4,
+            baud_rate: BaudRate::Bps1200,
+            ..Default::default()
+        };
+        assert!(params.validate_packet_len(FU4_MAX_PACKET_LEN).is_ok());
+        assert!(matches!(
+            params.validate_packet_len(FU4_MAX_PACKET_LEN + 1),
+            Err(crate::Error::PacketTooLong)
+        ));
+    }
+
+    #[test]
+    fn airtime() {
+        assert_eq!(airtime_us(0, AirBaudRate::Bps5000), 0);
+        // 60 bytes of 10 bits at 15000 bps
+        assert_eq!(airtime_us(60, AirBaudRate::Bps15000), 40_000);
+        assert_eq!(airtime_us(1, AirBaudRate::Bps236000), 43);
+    }
+
+    #[test]
+    fn transmission_time() {
+        let params = Parameters::default();
+        assert_eq!(params.transmission_time_ms(0), 0);
+        // 100 bytes take 66.7 ms at 15000 bps on the air, plus 80 ms in FU3
+        assert_eq!(params.transmission_time_ms(100), 147);
+        let params = Parameters {
+            mode: Mode::Fu4,
+            baud_rate: BaudRate::Bps1200,
+            ..Default::default()
+        };
+        // 10 bytes take 200 ms at 500 bps on the air, plus 1000 ms in FU4
+        assert_eq!(params.transmission_time_ms(10), 1200);
+    }
 }
@@ -0,0 +1,52 @@
+/// Parity bit of the serial data format
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Parity {
+    #[default]
+    /// No parity bit
+    None,
+    /// Odd parity
+    Odd,
+    /// Even parity
+    Even,
+}
+
+/// Stop bits of the serial data format
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum StopBits {
+    #[default]
+    /// One stop bit
+    One,
+    /// Two stop bits
+    Two,
+    /// One and a half stop bits
+    OneAndHalf,
+}
+
+/// Serial data format of HC-12, always with eight data bits
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SerialFormat {
+    /// Parity bit
+    pub parity: Parity,
+    /// Stop bits
+    pub stop_bits: StopBits,
+}
+
+impl SerialFormat {
+    /// Construct a serial format with eight data bits
+    pub fn new(parity: Parity, stop_bits: StopBits) -> Self {
+        Self { parity, stop_bits }
+    }
+
+    /// Bits on the serial line per byte, counting one and a half stop bits as two
+    pub fn bits_per_byte(&self) -> u32 {
+        let parity = match self.parity {
+            Parity::None => 0,
+            Parity::Odd | Parity::Even => 1,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two | StopBits::OneAndHalf => 2,
+        };
+        1 + 8 + parity + stop_bits
+    }
+}
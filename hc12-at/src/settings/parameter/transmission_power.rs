@@ -5,7 +5,7 @@ use num_traits::FromPrimitive;
 
 /// Transmission power
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum TransmissionPower {
     /// Power -1 dBm
     One = 1,
@@ -32,6 +32,7 @@ impl TransmissionPower {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for TransmissionPower {
     fn default() -> Self {
         Self::Eight
@@ -0,0 +1,311 @@
+//! AT commands and responses of Hc12 on the wire, in both directions.
+
+use core::convert::TryFrom;
+
+use num_traits::ToPrimitive;
+
+use crate::settings::command::MakeCommand;
+use crate::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, serial_format::SerialFormat,
+    transmission_power::TransmissionPower, OK_QUERY, OK_RESPONSE, QUERY_PARAMS_COMMAND,
+    RESET_SETTINGS_COMMAND, RESET_SETTINGS_RESPONSE, SLEEP_COMMAND, SLEEP_RESPONSE, VERSION_QUERY,
+};
+use crate::settings::query::MakeQuery;
+use crate::settings::response_parser::{parse_int, serial_format::parse_format, ResponseError};
+use crate::settings::version::FirmwareVersion;
+use crate::RawResponse;
+
+#[cfg(test)]
+mod test;
+
+/// AT command sent to Hc12
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// "AT"
+    Test,
+    /// "AT+Bxxxx"
+    SetBaudRate(BaudRate),
+    /// "AT+Cxxx"
+    SetChannel(Channel),
+    /// "AT+FUx"
+    SetMode(Mode),
+    /// "AT+Px"
+    SetPower(TransmissionPower),
+    /// "AT+Udps", data bits, parity and stop bits
+    SetSerialFormat(SerialFormat),
+    /// "AT+RB"
+    QueryBaudRate,
+    /// "AT+RC"
+    QueryChannel,
+    /// "AT+RF"
+    QueryMode,
+    /// "AT+RP"
+    QueryPower,
+    /// "AT+RU"
+    QuerySerialFormat,
+    /// "AT+RX"
+    QueryParameters,
+    /// "AT+V"
+    QueryVersion,
+    /// "AT+SLEEP"
+    Sleep,
+    /// "AT+DEFAULT"
+    Default,
+}
+
+/// Response sent by Hc12
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// "OK"
+    Ok,
+    /// "OK+Bxxxx", answering a query or acknowledging a command
+    BaudRate(BaudRate),
+    /// "OK+RCxxx", answering a query
+    Channel(Channel),
+    /// "OK+Cxxx", acknowledging a command
+    ChannelSet(Channel),
+    /// "OK+RP:±xdBm", answering a query
+    Power(TransmissionPower),
+    /// "OK+Px", acknowledging a command
+    PowerSet(TransmissionPower),
+    /// "OK+FUx", answering a query or acknowledging a command
+    Mode(Mode),
+    /// "OK+Udps", answering a query or acknowledging a command
+    SerialFormat(SerialFormat),
+    /// Firmware version, without line ending
+    Version(RawResponse),
+    /// "OK+SLEEP"
+    Sleep,
+    /// "OK+DEFAULT"
+    Default,
+}
+
+/// Bytes written into a buffer so far
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    /// Write `n` in decimal
+    fn number(&mut self, mut n: u32) {
+        let mut digits = [b'0'; 10];
+        let mut count = 0;
+        while n > 0 || count == 0 {
+            digits[count] = b'0' + (n % 10) as u8;
+            n /= 10;
+            count += 1;
+        }
+        digits[..count].reverse();
+        self.push(&digits[..count]);
+    }
+
+    fn finish(self) -> &'a [u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+/// Mode of a number, as in "FUx"
+fn mode_from_number(n: i32) -> Result<Mode, ResponseError> {
+    u8::try_from(n)
+        .ok()
+        .and_then(Mode::new)
+        .ok_or(ResponseError::OutOfRange(n))
+}
+
+/// Channel of a number, as in "Cxxx"
+fn channel_from_number(n: i32) -> Result<Channel, ResponseError> {
+    u8::try_from(n)
+        .ok()
+        .and_then(|byte| Channel::try_from(byte).ok())
+        .ok_or(ResponseError::OutOfRange(n))
+}
+
+/// Transmission power of a level, as in "Px"
+fn power_from_level(n: i32) -> Result<TransmissionPower, ResponseError> {
+    u8::try_from(n)
+        .ok()
+        .and_then(TransmissionPower::new)
+        .ok_or(ResponseError::OutOfRange(n))
+}
+
+/// Serial format of "dps\r\n"
+fn serial_format(rest: &[u8]) -> Result<SerialFormat, ResponseError> {
+    rest.strip_suffix(b"\r\n")
+        .ok_or(ResponseError::Malformed)
+        .and_then(parse_format)
+}
+
+impl Command {
+    /// Write the command into the buffer, including the line ending
+    pub fn encode<'a>(&self, buffer: &'a mut [u8; 16]) -> &'a [u8] {
+        let mut query = [0u8; 7];
+        let fixed: &[u8] = match self {
+            Command::SetBaudRate(rate) => return rate.make_command(buffer),
+            Command::SetChannel(channel) => return channel.make_command(buffer),
+            Command::SetMode(mode) => return mode.make_command(buffer),
+            Command::SetPower(power) => return power.make_command(buffer),
+            Command::SetSerialFormat(format) => return format.make_command(buffer),
+            Command::Test => &OK_QUERY,
+            Command::QueryBaudRate => {
+                BaudRate::make_query(&mut query);
+                &query
+            }
+            Command::QueryChannel => {
+                Channel::make_query(&mut query);
+                &query
+            }
+            Command::QueryMode => {
+                Mode::make_query(&mut query);
+                &query
+            }
+            Command::QueryPower => {
+                TransmissionPower::make_query(&mut query);
+                &query
+            }
+            Command::QuerySerialFormat => {
+                SerialFormat::make_query(&mut query);
+                &query
+            }
+            Command::QueryParameters => &QUERY_PARAMS_COMMAND,
+            Command::QueryVersion => &VERSION_QUERY,
+            Command::Sleep => &SLEEP_COMMAND,
+            Command::Default => &RESET_SETTINGS_COMMAND,
+        };
+        buffer[..fixed.len()].copy_from_slice(fixed);
+        &buffer[..fixed.len()]
+    }
+
+    /// Parse a complete command, including the line ending
+    pub fn decode(bytes: &[u8]) -> Result<Self, ResponseError> {
+        let fixed = [
+            (&OK_QUERY[..], Command::Test),
+            (&b"AT+RB\r\n"[..], Command::QueryBaudRate),
+            (&b"AT+RC\r\n"[..], Command::QueryChannel),
+            (&b"AT+RF\r\n"[..], Command::QueryMode),
+            (&b"AT+RP\r\n"[..], Command::QueryPower),
+            (&b"AT+RU\r\n"[..], Command::QuerySerialFormat),
+            (&QUERY_PARAMS_COMMAND[..], Command::QueryParameters),
+            (&VERSION_QUERY[..], Command::QueryVersion),
+            (&SLEEP_COMMAND[..], Command::Sleep),
+            (&RESET_SETTINGS_COMMAND[..], Command::Default),
+        ];
+        if let Some((_, command)) = fixed.iter().find(|(encoded, _)| *encoded == bytes) {
+            return Ok(command.clone());
+        }
+        if bytes.starts_with(b"AT+B") {
+            let rate = parse_int(bytes, b"AT+B", b"\r\n")?;
+            BaudRate::try_from(rate)
+                .map(Command::SetBaudRate)
+                .map_err(|_| ResponseError::OutOfRange(rate))
+        } else if bytes.starts_with(b"AT+C") {
+            channel_from_number(parse_int(bytes, b"AT+C", b"\r\n")?).map(Command::SetChannel)
+        } else if bytes.starts_with(b"AT+FU") {
+            mode_from_number(parse_int(bytes, b"AT+FU", b"\r\n")?).map(Command::SetMode)
+        } else if bytes.starts_with(b"AT+P") {
+            power_from_level(parse_int(bytes, b"AT+P", b"\r\n")?).map(Command::SetPower)
+        } else if let Some(rest) = bytes.strip_prefix(b"AT+U") {
+            serial_format(rest).map(Command::SetSerialFormat)
+        } else if bytes.starts_with(b"AT") {
+            Err(ResponseError::UnknownIdentifier)
+        } else {
+            Err(ResponseError::Malformed)
+        }
+    }
+}
+
+impl Response {
+    /// Write the response into the buffer, including the line ending.
+    /// The buffer fits the longest version, which is truncated to 32 bytes like a `RawResponse`.
+    pub fn encode<'a>(&self, buffer: &'a mut [u8; 34]) -> &'a [u8] {
+        let mut writer = Writer::new(buffer);
+        match self {
+            Response::Ok => return encode_fixed(writer, &OK_RESPONSE),
+            Response::Sleep => return encode_fixed(writer, &SLEEP_RESPONSE),
+            Response::Default => return encode_fixed(writer, &RESET_SETTINGS_RESPONSE),
+            Response::BaudRate(rate) => {
+                writer.push(b"OK+B");
+                writer.push(rate.into());
+            }
+            Response::Channel(channel) => {
+                writer.push(b"OK+RC");
+                writer.push(&<[u8; 3]>::from(channel));
+            }
+            Response::ChannelSet(channel) => {
+                writer.push(b"OK+C");
+                writer.push(&<[u8; 3]>::from(channel));
+            }
+            Response::Power(power) => {
+                let dbm = power.get_power_dbm();
+                writer.push(b"OK+RP:");
+                writer.push(if dbm < 0 { b"-" } else { b"+" });
+                writer.number(u32::from(dbm.unsigned_abs()));
+                writer.push(b"dBm");
+            }
+            Response::PowerSet(power) => {
+                writer.push(b"OK+P");
+                writer.number(u32::from(power.to_u8().unwrap_or_default()));
+            }
+            Response::Mode(mode) => {
+                writer.push(b"OK+FU");
+                writer.number(u32::from(mode.number()));
+            }
+            Response::SerialFormat(format) => {
+                writer.push(b"OK+U");
+                writer.push(&<[u8; 3]>::from(format));
+            }
+            Response::Version(version) => writer.push(version.as_bytes()),
+        }
+        writer.push(b"\r\n");
+        writer.finish()
+    }
+
+    /// Parse a complete response, including the line ending
+    pub fn decode(bytes: &[u8]) -> Result<Self, ResponseError> {
+        let fixed = [
+            (&OK_RESPONSE[..], Response::Ok),
+            (&SLEEP_RESPONSE[..], Response::Sleep),
+            (&RESET_SETTINGS_RESPONSE[..], Response::Default),
+        ];
+        if let Some((_, response)) = fixed.iter().find(|(encoded, _)| *encoded == bytes) {
+            return Ok(response.clone());
+        }
+        if bytes.starts_with(b"OK+B") {
+            BaudRate::try_from(bytes).map(Response::BaudRate)
+        } else if bytes.starts_with(b"OK+RC") {
+            Channel::try_from(bytes).map(Response::Channel)
+        } else if bytes.starts_with(b"OK+C") {
+            channel_from_number(parse_int(bytes, b"OK+C", b"\r\n")?).map(Response::ChannelSet)
+        } else if bytes.starts_with(b"OK+RP:") {
+            TransmissionPower::try_from(bytes).map(Response::Power)
+        } else if bytes.starts_with(b"OK+P") {
+            power_from_level(parse_int(bytes, b"OK+P", b"\r\n")?).map(Response::PowerSet)
+        } else if bytes.starts_with(b"OK+FU") {
+            Mode::try_from(bytes).map(Response::Mode)
+        } else if bytes.starts_with(b"OK+U") {
+            SerialFormat::try_from(bytes).map(Response::SerialFormat)
+        } else if bytes.starts_with(b"OK") {
+            Err(ResponseError::UnknownIdentifier)
+        } else if let Some(version) = bytes.strip_suffix(b"\r\n") {
+            FirmwareVersion::try_from(version)?;
+            Ok(Response::Version(RawResponse::new(version)))
+        } else {
+            Err(ResponseError::UnknownIdentifier)
+        }
+    }
+}
+
+/// Write a response which has no parameters
+fn encode_fixed<'a>(mut writer: Writer<'a>, response: &[u8]) -> &'a [u8] {
+    writer.push(response);
+    writer.finish()
+}
@@ -0,0 +1,130 @@
+use crate::settings::{
+    parameter::{
+        baudrate::BaudRate,
+        channel::Channel,
+        mode::Mode,
+        serial_format::{Parity, SerialFormat, StopBits},
+        transmission_power::TransmissionPower,
+    },
+    protocol::{Command, Response},
+    response_parser::ResponseError,
+};
+use crate::RawResponse;
+
+#[test]
+fn encode_commands() {
+    let commands: [(Command, &[u8]); 15] = [
+        (Command::Test, b"AT\r\n"),
+        (Command::SetBaudRate(BaudRate::Bps115200), b"AT+B115200\r\n"),
+        (
+            Command::SetChannel(Channel::new(21).unwrap()),
+            b"AT+C021\r\n",
+        ),
+        (Command::SetMode(Mode::Fu4), b"AT+FU4\r\n"),
+        (Command::SetPower(TransmissionPower::Eight), b"AT+P8\r\n"),
+        (
+            Command::SetSerialFormat(SerialFormat::new(Parity::Odd, StopBits::One)),
+            b"AT+U8O1\r\n",
+        ),
+        (Command::QueryBaudRate, b"AT+RB\r\n"),
+        (Command::QueryChannel, b"AT+RC\r\n"),
+        (Command::QueryMode, b"AT+RF\r\n"),
+        (Command::QueryPower, b"AT+RP\r\n"),
+        (Command::QuerySerialFormat, b"AT+RU\r\n"),
+        (Command::QueryParameters, b"AT+RX\r\n"),
+        (Command::QueryVersion, b"AT+V\r\n"),
+        (Command::Sleep, b"AT+SLEEP\r\n"),
+        (Command::Default, b"AT+DEFAULT\r\n"),
+    ];
+    let mut buffer = [0u8; 16];
+    for (command, encoded) in &commands {
+        assert_eq!(command.encode(&mut buffer), *encoded);
+        assert_eq!(Command::decode(encoded).as_ref(), Ok(command));
+    }
+}
+
+#[test]
+fn decode_command_errors() {
+    assert_eq!(
+        Command::decode(b"AT+C000\r\n"),
+        Err(ResponseError::OutOfRange(0))
+    );
+    assert_eq!(
+        Command::decode(b"AT+B9601\r\n"),
+        Err(ResponseError::OutOfRange(9601))
+    );
+    assert_eq!(Command::decode(b"AT+FU\r\n"), Err(ResponseError::Malformed));
+    assert_eq!(
+        Command::decode(b"AT+U8N\r\n"),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        Command::decode(b"AT+X\r\n"),
+        Err(ResponseError::UnknownIdentifier)
+    );
+    assert_eq!(Command::decode(b"hello"), Err(ResponseError::Malformed));
+}
+
+#[test]
+fn encode_responses() {
+    let responses: [(Response, &[u8]); 13] = [
+        (Response::Ok, b"OK\r\n"),
+        (Response::BaudRate(BaudRate::Bps9600), b"OK+B9600\r\n"),
+        (Response::Channel(Channel::new(1).unwrap()), b"OK+RC001\r\n"),
+        (
+            Response::ChannelSet(Channel::new(127).unwrap()),
+            b"OK+C127\r\n",
+        ),
+        (
+            Response::Power(TransmissionPower::Eight),
+            b"OK+RP:+20dBm\r\n",
+        ),
+        (Response::Power(TransmissionPower::One), b"OK+RP:-1dBm\r\n"),
+        (Response::PowerSet(TransmissionPower::Three), b"OK+P3\r\n"),
+        (Response::Mode(Mode::Fu2), b"OK+FU2\r\n"),
+        (
+            Response::SerialFormat(SerialFormat::new(Parity::Even, StopBits::Two)),
+            b"OK+U8E2\r\n",
+        ),
+        (
+            Response::Version(RawResponse::new(b"www.hc01.com HC-12_V2.6")),
+            b"www.hc01.com HC-12_V2.6\r\n",
+        ),
+        (
+            Response::Version(RawResponse::new(b"XY-12_V1.0")),
+            b"XY-12_V1.0\r\n",
+        ),
+        (Response::Sleep, b"OK+SLEEP\r\n"),
+        (Response::Default, b"OK+DEFAULT\r\n"),
+    ];
+    let mut buffer = [0u8; 34];
+    for (response, encoded) in &responses {
+        assert_eq!(response.encode(&mut buffer), *encoded);
+        assert_eq!(Response::decode(encoded).as_ref(), Ok(response));
+    }
+}
+
+#[test]
+fn decode_response_errors() {
+    assert_eq!(
+        Response::decode(b"OK+FU5\r\n"),
+        Err(ResponseError::OutOfRange(5))
+    );
+    assert_eq!(
+        Response::decode(b"OK+P9\r\n"),
+        Err(ResponseError::OutOfRange(9))
+    );
+    assert_eq!(Response::decode(b"OK+B\r\n"), Err(ResponseError::Malformed));
+    assert_eq!(
+        Response::decode(b"OK+WHAT\r\n"),
+        Err(ResponseError::UnknownIdentifier)
+    );
+    assert_eq!(
+        Response::decode(b"ERROR\r\n"),
+        Err(ResponseError::UnknownIdentifier)
+    );
+    assert_eq!(
+        Response::decode(b"HC-12_V2\r\n"),
+        Err(ResponseError::Malformed)
+    );
+}
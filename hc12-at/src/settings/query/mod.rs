@@ -1,6 +1,7 @@
 pub(crate) mod baudrate;
 pub(crate) mod channel;
 pub(crate) mod mode;
+pub(crate) mod serial_format;
 pub(crate) mod transmission_power;
 
 #[cfg(test)]
@@ -0,0 +1,9 @@
+use crate::settings::parameter::serial_format::SerialFormat;
+
+use super::MakeQuery;
+
+impl MakeQuery for SerialFormat {
+    fn make_query(buffer: &mut [u8; 7]) {
+        buffer[..7].copy_from_slice(b"AT+RU\r\n");
+    }
+}
@@ -1,6 +1,7 @@
 use crate::settings::{
     parameter::{
-        baudrate::BaudRate, channel::Channel, mode::Mode, transmission_power::TransmissionPower,
+        baudrate::BaudRate, channel::Channel, mode::Mode, serial_format::SerialFormat,
+        transmission_power::TransmissionPower,
     },
     query::MakeQuery,
 };
@@ -16,4 +17,6 @@ fn query_single_param() {
     assert_eq!(b"AT+RF\r\n", &buffer);
     TransmissionPower::make_query(&mut buffer);
     assert_eq!(b"AT+RP\r\n", &buffer);
+    SerialFormat::make_query(&mut buffer);
+    assert_eq!(b"AT+RU\r\n", &buffer);
 }
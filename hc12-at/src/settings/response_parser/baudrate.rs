@@ -1,18 +1,21 @@
 use core::convert::TryFrom;
 
-use at_commands::parser::{CommandParser, ParseError};
-
 use crate::settings::parameter::baudrate::BaudRate;
 
+use super::{parse_int, ParseAcknowledgement, ResponseError};
+
 impl TryFrom<&[u8]> for BaudRate {
-    type Error = ParseError;
+    type Error = ResponseError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let result = CommandParser::parse(value)
-            .expect_identifier(b"OK+B")
-            .expect_int_parameter()
-            .expect_identifier(b"\r\n")
-            .finish()?;
-        Ok(BaudRate::try_from(result.0).unwrap())
+        let rate = parse_int(value, b"OK+B", b"\r\n")?;
+        BaudRate::try_from(rate).map_err(|_| ResponseError::OutOfRange(rate))
+    }
+}
+
+impl ParseAcknowledgement for BaudRate {
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self> {
+        let rate = parse_int(response, b"OK+B", b"\r\n").ok()?;
+        BaudRate::try_from(rate).ok()
     }
 }
@@ -1,20 +1,25 @@
 use core::convert::TryFrom;
 
-use at_commands::parser::{CommandParser, ParseError};
-
 use crate::settings::parameter::channel::Channel;
 
+use super::{parse_int, ParseAcknowledgement, ResponseError};
+
 impl TryFrom<&[u8]> for Channel {
-    type Error = ParseError;
+    type Error = ResponseError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let result = CommandParser::parse(value)
-            .expect_identifier(b"OK+RC")
-            .expect_int_parameter()
-            .expect_identifier(b"\r\n")
-            .finish()
-            .unwrap();
-        let byte: u8 = result.0 as u8;
-        Ok(Channel::try_from(byte).unwrap())
+        let channel = parse_int(value, b"OK+RC", b"\r\n")?;
+        u8::try_from(channel)
+            .ok()
+            .and_then(|byte| Channel::try_from(byte).ok())
+            .ok_or(ResponseError::OutOfRange(channel))
+    }
+}
+
+impl ParseAcknowledgement for Channel {
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self> {
+        let channel = parse_int(response, b"OK+C", b"\r\n").ok()?;
+        let byte = u8::try_from(channel).ok()?;
+        Channel::try_from(byte).ok()
     }
 }
@@ -3,7 +3,48 @@
 pub(crate) mod baudrate;
 pub(crate) mod channel;
 pub(crate) mod mode;
+pub(crate) mod serial_format;
 pub(crate) mod transmission_power;
 
+/// Streaming parser emitting events line by line
+pub mod stream;
+
 #[cfg(test)]
 mod test;
+
+use at_commands::parser::CommandParser;
+
+/// Reason why a response of Hc12 could not be parsed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResponseError {
+    /// Response does not follow the syntax of the expected response
+    Malformed,
+    /// Response starts with an identifier other than the expected one
+    UnknownIdentifier,
+    /// Response contains a number outside the range of valid values
+    OutOfRange(i32),
+}
+
+/// Parse a response consisting of `identifier`, a number, and `suffix`
+pub(crate) fn parse_int(
+    response: &[u8],
+    identifier: &[u8],
+    suffix: &[u8],
+) -> Result<i32, ResponseError> {
+    if !response.starts_with(identifier) {
+        return Err(ResponseError::UnknownIdentifier);
+    }
+    let (value,) = CommandParser::parse(response)
+        .expect_identifier(identifier)
+        .expect_int_parameter()
+        .expect_identifier(suffix)
+        .finish()
+        .map_err(|_| ResponseError::Malformed)?;
+    Ok(value)
+}
+
+/// Parse the acknowledgement the Hc12 sends after a command changed a parameter
+pub trait ParseAcknowledgement: Sized {
+    /// Return the acknowledged value, if the response is a valid acknowledgement
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self>;
+}
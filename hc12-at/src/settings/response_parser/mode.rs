@@ -1,24 +1,23 @@
 use core::convert::TryFrom;
 
-use at_commands::parser::{CommandParser, ParseError};
-
 use crate::settings::parameter::mode::Mode;
 
+use super::{parse_int, ParseAcknowledgement, ResponseError};
+
 impl TryFrom<&[u8]> for Mode {
-    type Error = ParseError;
+    type Error = ResponseError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let result = CommandParser::parse(value)
-            .expect_identifier(b"OK+FU")
-            .expect_int_parameter()
-            .expect_identifier(b"\r\n")
-            .finish();
-        match result.unwrap() {
-            (1,) => Ok(Mode::Fu1),
-            (2,) => Ok(Mode::Fu2),
-            (3,) => Ok(Mode::Fu3),
-            (4,) => Ok(Mode::Fu4),
-            _n => panic!("Invalid mode"),
-        }
+        let n = parse_int(value, b"OK+FU", b"\r\n")?;
+        u8::try_from(n)
+            .ok()
+            .and_then(Mode::new)
+            .ok_or(ResponseError::OutOfRange(n))
+    }
+}
+
+impl ParseAcknowledgement for Mode {
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self> {
+        Mode::try_from(response).ok()
     }
 }
@@ -0,0 +1,52 @@
+use core::convert::TryFrom;
+
+use crate::settings::parameter::serial_format::{Parity, SerialFormat, StopBits};
+
+use super::{ParseAcknowledgement, ResponseError};
+
+/// Parse data bits, parity and stop bits, as in "8N1"
+pub(crate) fn parse_format(format: &[u8]) -> Result<SerialFormat, ResponseError> {
+    let (data_bits, parity, stop_bits) = match format {
+        [d, p, s] => (*d, *p, *s),
+        _ => return Err(ResponseError::Malformed),
+    };
+    if !data_bits.is_ascii_digit() || !stop_bits.is_ascii_digit() {
+        return Err(ResponseError::Malformed);
+    }
+    if data_bits != b'8' {
+        return Err(ResponseError::OutOfRange(i32::from(data_bits - b'0')));
+    }
+    let parity = match parity {
+        b'N' => Parity::None,
+        b'O' => Parity::Odd,
+        b'E' => Parity::Even,
+        _ => return Err(ResponseError::Malformed),
+    };
+    let stop_bits = match stop_bits {
+        b'1' => StopBits::One,
+        b'2' => StopBits::Two,
+        b'3' => StopBits::OneAndHalf,
+        n => return Err(ResponseError::OutOfRange(i32::from(n - b'0'))),
+    };
+    Ok(SerialFormat::new(parity, stop_bits))
+}
+
+impl TryFrom<&[u8]> for SerialFormat {
+    type Error = ResponseError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let format = value
+            .strip_prefix(b"OK+U")
+            .ok_or(ResponseError::UnknownIdentifier)?;
+        let format = format
+            .strip_suffix(b"\r\n")
+            .ok_or(ResponseError::Malformed)?;
+        parse_format(format)
+    }
+}
+
+impl ParseAcknowledgement for SerialFormat {
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self> {
+        SerialFormat::try_from(response).ok()
+    }
+}
@@ -0,0 +1,138 @@
+use crate::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, serial_format::SerialFormat,
+    transmission_power::TransmissionPower,
+};
+use crate::settings::protocol::Response;
+use crate::RawResponse;
+
+/// Maximum length of a line, longer lines are truncated
+const LINE_LEN: usize = 32;
+
+/// Response of Hc12, parsed from a single line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// "OK"
+    Ok,
+    /// Baud rate, reported or acknowledged
+    BaudRate(BaudRate),
+    /// Channel, reported or acknowledged
+    Channel(Channel),
+    /// Transmission power, reported or acknowledged
+    Power(TransmissionPower),
+    /// Operation mode, reported or acknowledged
+    Mode(Mode),
+    /// Serial format, reported or acknowledged
+    SerialFormat(SerialFormat),
+    /// Firmware version, without line ending
+    Version(RawResponse),
+    /// Hc12 goes to sleep
+    Sleep,
+    /// Hc12 reset its settings
+    Default,
+    /// Line which is not a known response
+    Unknown(RawResponse),
+}
+
+/// Streaming parser for responses of Hc12.
+///
+/// Bytes are fed one at a time, so the parser can be driven from an interrupt or DMA callback.
+/// Stale bytes before a response on the same line are skipped.
+#[derive(Debug, Clone)]
+pub struct ResponseParser {
+    line: [u8; LINE_LEN],
+    len: usize,
+}
+
+impl Default for ResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseParser {
+    /// Construct a parser with an empty line
+    pub const fn new() -> Self {
+        Self {
+            line: [0; LINE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Drop the bytes of the current line
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    /// Feed a byte. Returns an event when the byte completed a line.
+    pub fn push(&mut self, byte: u8) -> Option<Event> {
+        if self.len < LINE_LEN {
+            self.line[self.len] = byte;
+            self.len += 1;
+        } else {
+            // Keep the end of an overlong line, where a response may still follow stale bytes
+            self.line.copy_within(1.., 0);
+            self.line[LINE_LEN - 1] = byte;
+        }
+        if byte != b'\n' {
+            return None;
+        }
+        let event = parse_line(&self.line[..self.len]);
+        self.len = 0;
+        Some(event)
+    }
+}
+
+/// Parse a complete line, skipping anything before the start of a response
+fn parse_line(line: &[u8]) -> Event {
+    let start = find(line, b"OK")
+        .or_else(|| version_start(line))
+        .unwrap_or(0);
+    match Response::decode(&line[start..]) {
+        Ok(response) => response.into(),
+        Err(_) => Event::Unknown(RawResponse::new(line)),
+    }
+}
+
+impl From<Response> for Event {
+    fn from(response: Response) -> Self {
+        match response {
+            Response::Ok => Event::Ok,
+            Response::BaudRate(rate) => Event::BaudRate(rate),
+            Response::Channel(channel) | Response::ChannelSet(channel) => Event::Channel(channel),
+            Response::Power(power) | Response::PowerSet(power) => Event::Power(power),
+            Response::Mode(mode) => Event::Mode(mode),
+            Response::SerialFormat(format) => Event::SerialFormat(format),
+            Response::Version(version) => Event::Version(version),
+            Response::Sleep => Event::Sleep,
+            Response::Default => Event::Default,
+        }
+    }
+}
+
+/// Start of a version line like "www.hc01.com HC-12_V2.6", after any stale bytes.
+///
+/// The model token ends with the version after "_V", and the vendor is the token before it, if any.
+/// Both consist of printable characters, so the line starts after the last other byte before them.
+fn version_start(line: &[u8]) -> Option<usize> {
+    let separator = find(line, b"_V")?;
+    let token_start = |end: usize| {
+        line[..end]
+            .iter()
+            .rposition(|b| !b.is_ascii_graphic())
+            .map_or(0, |position| position + 1)
+    };
+    let model = token_start(separator);
+    match model.checked_sub(1) {
+        Some(space) if line[space] == b' ' && token_start(space) < space => {
+            Some(token_start(space))
+        }
+        _ => Some(model),
+    }
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -1,8 +1,19 @@
 use core::convert::TryFrom;
 
-use crate::settings::parameter::{
-    baudrate::BaudRate, channel::Channel, mode::Mode, transmission_power::TransmissionPower,
+use crate::settings::{
+    parameter::{
+        baudrate::BaudRate,
+        channel::Channel,
+        mode::Mode,
+        serial_format::{Parity, SerialFormat, StopBits},
+        transmission_power::TransmissionPower,
+    },
+    response_parser::{
+        stream::{Event, ResponseParser},
+        ParseAcknowledgement, ResponseError,
+    },
 };
+use crate::RawResponse;
 
 #[test]
 fn parse_baudrate() {
@@ -33,7 +44,60 @@ fn parse_channel() {
 #[test]
 fn parse_channel_error() {
     let response = b"OK+RC000\r\n";
-    assert!(Channel::try_from(&response[..]).is_err());
+    assert_eq!(
+        Channel::try_from(&response[..]),
+        Err(ResponseError::OutOfRange(0))
+    );
+    let response = b"OK+RC300\r\n";
+    assert_eq!(
+        Channel::try_from(&response[..]),
+        Err(ResponseError::OutOfRange(300))
+    );
+}
+
+#[test]
+fn parse_baudrate_error() {
+    let response = b"OK+B9601\r\n";
+    assert_eq!(
+        BaudRate::try_from(&response[..]),
+        Err(ResponseError::OutOfRange(9601))
+    );
+}
+
+#[test]
+fn parse_malformed() {
+    assert_eq!(
+        BaudRate::try_from(&b"OK+B9600"[..]),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        Channel::try_from(&b"OK+RCabc\r\n"[..]),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        Mode::try_from(&b"OK+FU3\r"[..]),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        TransmissionPower::try_from(&b"OK+RP:+20\r\n"[..]),
+        Err(ResponseError::Malformed)
+    );
+}
+
+#[test]
+fn parse_unknown_identifier() {
+    assert_eq!(
+        BaudRate::try_from(&b"OK+X9600\r\n"[..]),
+        Err(ResponseError::UnknownIdentifier)
+    );
+    assert_eq!(
+        Channel::try_from(&b"\xfe\xff\r\n"[..]),
+        Err(ResponseError::UnknownIdentifier)
+    );
+    assert_eq!(
+        Mode::try_from(&b""[..]),
+        Err(ResponseError::UnknownIdentifier)
+    );
 }
 
 #[test]
@@ -55,7 +119,10 @@ fn parse_mode() {
 #[test]
 fn parse_mode_error() {
     let response = b"OK+FU5\r\n";
-    assert!(Mode::try_from(&response[..]).is_err());
+    assert_eq!(
+        Mode::try_from(&response[..]),
+        Err(ResponseError::OutOfRange(5))
+    );
 }
 
 #[test]
@@ -74,7 +141,52 @@ fn parse_power() {
 #[test]
 fn parse_power_error() {
     let response = b"OK+RP:-2dBm\r\n";
-    assert!(TransmissionPower::try_from(&response[..]).is_err());
+    assert_eq!(
+        TransmissionPower::try_from(&response[..]),
+        Err(ResponseError::OutOfRange(-2))
+    );
+}
+
+#[test]
+fn parse_serial_format() {
+    let response = b"OK+U8N1\r\n";
+    assert_eq!(
+        SerialFormat::try_from(&response[..]),
+        Ok(SerialFormat::default())
+    );
+    let response = b"OK+U8E3\r\n";
+    assert_eq!(
+        SerialFormat::try_from(&response[..]),
+        Ok(SerialFormat::new(Parity::Even, StopBits::OneAndHalf))
+    );
+    assert_eq!(
+        SerialFormat::parse_acknowledgement(b"OK+U8O2\r\n"),
+        Some(SerialFormat::new(Parity::Odd, StopBits::Two))
+    );
+}
+
+#[test]
+fn parse_serial_format_error() {
+    assert_eq!(
+        SerialFormat::try_from(&b"OK+U7N1\r\n"[..]),
+        Err(ResponseError::OutOfRange(7))
+    );
+    assert_eq!(
+        SerialFormat::try_from(&b"OK+U8N4\r\n"[..]),
+        Err(ResponseError::OutOfRange(4))
+    );
+    assert_eq!(
+        SerialFormat::try_from(&b"OK+U8X1\r\n"[..]),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        SerialFormat::try_from(&b"OK+U8N1"[..]),
+        Err(ResponseError::Malformed)
+    );
+    assert_eq!(
+        SerialFormat::try_from(&b"OK+B9600\r\n"[..]),
+        Err(ResponseError::UnknownIdentifier)
+    );
 }
 
 #[test]
@@ -96,3 +208,111 @@ fn parse_baudrate_from_i32() {
         .collect();
     assert_eq!(&expected, &result[..]);
 }
+
+#[test]
+fn parse_acknowledgements() {
+    assert_eq!(
+        Some(BaudRate::Bps9600),
+        BaudRate::parse_acknowledgement(b"OK+B9600\r\n")
+    );
+    assert_eq!(
+        Some(Channel::new(21).unwrap()),
+        Channel::parse_acknowledgement(b"OK+C021\r\n")
+    );
+    assert_eq!(Some(Mode::Fu3), Mode::parse_acknowledgement(b"OK+FU3\r\n"));
+    assert_eq!(
+        Some(TransmissionPower::Eight),
+        TransmissionPower::parse_acknowledgement(b"OK+P8\r\n")
+    );
+}
+
+#[test]
+fn parse_acknowledgement_errors() {
+    assert_eq!(None, BaudRate::parse_acknowledgement(b"OK+B9601\r\n"));
+    assert_eq!(None, Channel::parse_acknowledgement(b"OK+C000\r\n"));
+    assert_eq!(None, Channel::parse_acknowledgement(b"OK+RC001\r\n"));
+    assert_eq!(None, Mode::parse_acknowledgement(b"OK+FU5\r\n"));
+    assert_eq!(None, TransmissionPower::parse_acknowledgement(b"OK+P9\r\n"));
+}
+
+fn feed(parser: &mut ResponseParser, bytes: &[u8]) -> Vec<Event> {
+    bytes.iter().filter_map(|byte| parser.push(*byte)).collect()
+}
+
+#[test]
+fn stream_events() {
+    let mut parser = ResponseParser::new();
+    let events = feed(
+        &mut parser,
+        b"OK\r\nOK+B9600\r\nOK+RC001\r\nOK+RP:+20dBm\r\nOK+FU3\r\nHC-12_V2.4\r\nOK+SLEEP\r\nOK+DEFAULT\r\n",
+    );
+    assert_eq!(
+        events,
+        [
+            Event::Ok,
+            Event::BaudRate(BaudRate::Bps9600),
+            Event::Channel(Channel::new(1).unwrap()),
+            Event::Power(TransmissionPower::Eight),
+            Event::Mode(Mode::Fu3),
+            Event::Version(RawResponse::new(b"HC-12_V2.4")),
+            Event::Sleep,
+            Event::Default,
+        ]
+    );
+}
+
+#[test]
+fn stream_acknowledgements() {
+    let mut parser = ResponseParser::default();
+    let events = feed(&mut parser, b"OK+C021\r\nOK+P3\r\nOK+B1200\r\n");
+    assert_eq!(
+        events,
+        [
+            Event::Channel(Channel::new(21).unwrap()),
+            Event::Power(TransmissionPower::Three),
+            Event::BaudRate(BaudRate::Bps1200),
+        ]
+    );
+}
+
+#[test]
+fn stream_skips_stale_bytes() {
+    let mut parser = ResponseParser::new();
+    assert_eq!(feed(&mut parser, b"\x00\xfedataOK\r"), []);
+    assert_eq!(parser.push(b'\n'), Some(Event::Ok));
+    let mut stale = vec![b'x'; 100];
+    stale.extend_from_slice(b"OK+FU2\r\n");
+    assert_eq!(feed(&mut parser, &stale), [Event::Mode(Mode::Fu2)]);
+}
+
+#[test]
+fn stream_skips_stale_bytes_before_version() {
+    let mut parser = ResponseParser::new();
+    assert_eq!(
+        feed(&mut parser, b"\x00\xfe\x80www.hc01.com HC-12_V2.6\r\n"),
+        [Event::Version(RawResponse::new(b"www.hc01.com HC-12_V2.6"))]
+    );
+    assert_eq!(
+        feed(&mut parser, b"\xfe \x80HC-12_V1.1\r\n"),
+        [Event::Version(RawResponse::new(b"HC-12_V1.1"))]
+    );
+}
+
+#[test]
+fn stream_unknown_lines() {
+    let mut parser = ResponseParser::new();
+    let events = feed(&mut parser, b"ERROR\r\nOK+FU5\r\n");
+    assert_eq!(
+        events,
+        [
+            Event::Unknown(RawResponse::new(b"ERROR\r\n")),
+            Event::Unknown(RawResponse::new(b"OK+FU5\r\n")),
+        ]
+    );
+    parser.push(b'O');
+    parser.reset();
+    assert_eq!(
+        feed(&mut parser, b"K\r\n"),
+        [Event::Unknown(RawResponse::new(b"K\r\n"))]
+    );
+}
@@ -1,18 +1,21 @@
 use core::convert::TryFrom;
 
-use at_commands::parser::{CommandParser, ParseError};
-
 use crate::settings::parameter::transmission_power::TransmissionPower;
 
+use super::{parse_int, ParseAcknowledgement, ResponseError};
+
 impl TryFrom<&[u8]> for TransmissionPower {
-    type Error = ParseError;
+    type Error = ResponseError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let result = CommandParser::parse(value)
-            .expect_identifier(b"OK+RP:")
-            .expect_int_parameter()
-            .expect_identifier(b"dBm\r\n")
-            .finish()?;
-        Ok(TransmissionPower::try_from(result.0).unwrap())
+        let dbm = parse_int(value, b"OK+RP:", b"dBm\r\n")?;
+        TransmissionPower::try_from(dbm).map_err(|_| ResponseError::OutOfRange(dbm))
+    }
+}
+
+impl ParseAcknowledgement for TransmissionPower {
+    fn parse_acknowledgement(response: &[u8]) -> Option<Self> {
+        let level = parse_int(response, b"OK+P", b"\r\n").ok()?;
+        TransmissionPower::new(u8::try_from(level).ok()?)
     }
 }
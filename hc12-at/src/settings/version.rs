@@ -0,0 +1,214 @@
+use core::convert::TryFrom;
+
+use num_traits::ToPrimitive;
+
+use crate::settings::parameter::{mode::Mode, transmission_power::TransmissionPower};
+use crate::settings::response_parser::ResponseError;
+use crate::RawResponse;
+
+/// All transmission power levels, in ascending order
+const POWER_LEVELS: [TransmissionPower; 8] = [
+    TransmissionPower::One,
+    TransmissionPower::Two,
+    TransmissionPower::Three,
+    TransmissionPower::Four,
+    TransmissionPower::Five,
+    TransmissionPower::Six,
+    TransmissionPower::Seven,
+    TransmissionPower::Eight,
+];
+
+/// Firmware version reported by Hc12, like "www.hc01.com HC-12_V2.6"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareVersion {
+    vendor: RawResponse,
+    model: RawResponse,
+    /// Major version
+    pub major: u8,
+    /// Minor version
+    pub minor: u8,
+}
+
+impl FirmwareVersion {
+    /// Vendor, like "www.hc01.com", or empty if the firmware does not report one
+    pub fn vendor(&self) -> &[u8] {
+        self.vendor.as_bytes()
+    }
+
+    /// Model, like "HC-12"
+    pub fn model(&self) -> &[u8] {
+        self.model.as_bytes()
+    }
+
+    /// Features implemented by this firmware.
+    ///
+    /// The datasheet of HC-12 documents FU4, "AT+Udps" and 8 power levels but no history of firmware versions,
+    /// so this only checks the model name and every HC-12 firmware is assumed to implement all of them.
+    /// Other models, like clones, are assumed to implement only the basic commands,
+    /// see `Hc12::set_capabilities` to enable more or to limit the power levels.
+    pub fn capabilities(&self) -> Capabilities {
+        if self.model() == b"HC-12" {
+            Capabilities::ALL
+        } else {
+            Capabilities::BASIC
+        }
+    }
+}
+
+/// Parse a version response, with or without line ending
+impl TryFrom<&[u8]> for FirmwareVersion {
+    type Error = ResponseError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let line = value.strip_suffix(b"\r\n").unwrap_or(value);
+        let (vendor, name) = match line.iter().rposition(|b| *b == b' ') {
+            Some(space) => (&line[..space], &line[space + 1..]),
+            None => (&line[..0], line),
+        };
+        let separator = name
+            .windows(2)
+            .position(|window| window == b"_V")
+            .ok_or(ResponseError::UnknownIdentifier)?;
+        let (model, number) = (&name[..separator], &name[separator + 2..]);
+        let dot = number
+            .iter()
+            .position(|b| *b == b'.')
+            .ok_or(ResponseError::Malformed)?;
+        Ok(FirmwareVersion {
+            vendor: RawResponse::new(vendor),
+            model: RawResponse::new(model),
+            major: parse_u8(&number[..dot])?,
+            minor: parse_u8(&number[dot + 1..])?,
+        })
+    }
+}
+
+/// Parse a decimal number of at most three digits
+fn parse_u8(digits: &[u8]) -> Result<u8, ResponseError> {
+    if digits.is_empty() || digits.len() > 3 || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(ResponseError::Malformed);
+    }
+    let n = digits
+        .iter()
+        .fold(0i32, |n, digit| n * 10 + i32::from(digit - b'0'));
+    u8::try_from(n).map_err(|_| ResponseError::OutOfRange(n))
+}
+
+/// Features implemented by a firmware revision
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether FU4 mode is supported
+    pub fu4: bool,
+    /// Whether the serial format can be set with "AT+Udps"
+    pub serial_format: bool,
+    /// Highest transmission power
+    pub max_power: TransmissionPower,
+}
+
+impl Capabilities {
+    /// Everything this driver knows about, assumed while the firmware version is unknown
+    pub const ALL: Capabilities = Capabilities {
+        fu4: true,
+        serial_format: true,
+        max_power: TransmissionPower::Eight,
+    };
+
+    /// Commands every known firmware implements, assumed for unknown models
+    pub const BASIC: Capabilities = Capabilities {
+        fu4: false,
+        serial_format: false,
+        max_power: TransmissionPower::Eight,
+    };
+
+    /// Whether the mode is supported
+    pub fn supports_mode(&self, mode: Mode) -> bool {
+        mode != Mode::Fu4 || self.fu4
+    }
+
+    /// Supported transmission power levels, in ascending order
+    pub fn power_levels(&self) -> &'static [TransmissionPower] {
+        let count = self.max_power.to_usize().unwrap_or(POWER_LEVELS.len());
+        &POWER_LEVELS[..count]
+    }
+
+    /// Whether the transmission power level is supported
+    pub fn supports_power(&self, power: TransmissionPower) -> bool {
+        self.power_levels().contains(&power)
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::ALL
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::convert::TryFrom;
+
+    use crate::settings::parameter::{mode::Mode, transmission_power::TransmissionPower};
+    use crate::settings::response_parser::ResponseError;
+
+    use super::{Capabilities, FirmwareVersion};
+
+    #[test]
+    fn parse_version() {
+        let version = FirmwareVersion::try_from(&b"www.hc01.com HC-12_V2.6\r\n"[..]).unwrap();
+        assert_eq!(version.vendor(), b"www.hc01.com");
+        assert_eq!(version.model(), b"HC-12");
+        assert_eq!((version.major, version.minor), (2, 6));
+
+        let version = FirmwareVersion::try_from(&b"HC-12_V1.1"[..]).unwrap();
+        assert_eq!(version.vendor(), b"");
+        assert_eq!(version.model(), b"HC-12");
+        assert_eq!((version.major, version.minor), (1, 1));
+    }
+
+    #[test]
+    fn parse_version_error() {
+        assert_eq!(
+            FirmwareVersion::try_from(&b"OK\r\n"[..]),
+            Err(ResponseError::UnknownIdentifier)
+        );
+        assert_eq!(
+            FirmwareVersion::try_from(&b"HC-12_V26\r\n"[..]),
+            Err(ResponseError::Malformed)
+        );
+        assert_eq!(
+            FirmwareVersion::try_from(&b"HC-12_V2.x\r\n"[..]),
+            Err(ResponseError::Malformed)
+        );
+        assert_eq!(
+            FirmwareVersion::try_from(&b"HC-12_V300.1\r\n"[..]),
+            Err(ResponseError::OutOfRange(300))
+        );
+    }
+
+    #[test]
+    fn capabilities() {
+        let version = FirmwareVersion::try_from(&b"www.hc01.com HC-12_V2.6"[..]).unwrap();
+        assert_eq!(version.capabilities(), Capabilities::ALL);
+
+        let version = FirmwareVersion::try_from(&b"HC-12_V1.1"[..]).unwrap();
+        assert_eq!(version.capabilities(), Capabilities::ALL);
+        assert_eq!(version.capabilities().power_levels().len(), 8);
+
+        let version = FirmwareVersion::try_from(&b"XY-12_V2.6"[..]).unwrap();
+        let capabilities = version.capabilities();
+        assert_eq!(capabilities, Capabilities::BASIC);
+        assert!(!capabilities.supports_mode(Mode::Fu4));
+        assert!(capabilities.supports_mode(Mode::Fu3));
+    }
+
+    #[test]
+    fn power_levels() {
+        let capabilities = Capabilities {
+            max_power: TransmissionPower::Five,
+            ..Capabilities::ALL
+        };
+        assert_eq!(capabilities.power_levels().len(), 5);
+        assert!(capabilities.supports_power(TransmissionPower::Five));
+        assert!(!capabilities.supports_power(TransmissionPower::Six));
+    }
+}
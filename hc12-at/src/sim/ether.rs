@@ -0,0 +1,260 @@
+//! Radio medium connecting several virtual Hc12 modules.
+//!
+//! The ether runs on a virtual clock which only moves on `Ether::advance`.
+//! Bytes a node writes in transparent mode become a packet which is on the air for as long as the air baud rate needs to send it.
+//! When it lands, it is delivered to every other node on the same channel, mode and air baud rate which is awake and in transparent mode.
+//! Packets of the same link which are on the air at the same time collide and reach nobody.
+
+use crate::settings::parameter::{
+    baudrate::AirBaudRate,
+    channel::Channel,
+    mode::Mode,
+    parameters::{airtime_us, Parameters},
+};
+
+use super::VirtualHc12;
+
+/// Behaviour of the radio medium
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EtherConfig {
+    /// Probability that a receiver misses a packet, from 0 to 1
+    pub packet_loss: f64,
+    /// Probability that a single bit is flipped at a receiver, from 0 to 1
+    pub bit_error_rate: f64,
+    /// Seed of the random numbers for loss and bit errors, so runs can be repeated
+    pub seed: u64,
+}
+
+impl Default for EtherConfig {
+    /// A perfect medium
+    fn default() -> Self {
+        Self {
+            packet_loss: 0.0,
+            bit_error_rate: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+/// Counters of what happened on the medium
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct EtherStats {
+    /// Packets sent by any node
+    pub sent: usize,
+    /// Packets which reached a listening receiver, possibly with bit errors
+    pub delivered: usize,
+    /// Packets a receiver missed due to packet loss
+    pub lost: usize,
+    /// Packets destroyed by a collision
+    pub collided: usize,
+}
+
+/// Nodes can only hear each other if all of these agree
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Link {
+    channel: Channel,
+    mode: Mode,
+    air_baud_rate: AirBaudRate,
+}
+
+impl From<&Parameters> for Link {
+    fn from(params: &Parameters) -> Self {
+        Self {
+            channel: params.channel,
+            mode: params.mode,
+            air_baud_rate: params.get_air_baud_rate(),
+        }
+    }
+}
+
+/// Packet on the air
+#[derive(Debug)]
+struct Packet {
+    sender: usize,
+    link: Link,
+    start_us: u64,
+    end_us: u64,
+    bytes: Vec<u8>,
+    collided: bool,
+}
+
+/// Small deterministic random number generator (xorshift64*)
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero
+        Self(seed.max(1))
+    }
+
+    /// Uniformly distributed number in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+}
+
+/// In-memory radio medium
+#[derive(Debug)]
+pub struct Ether {
+    config: EtherConfig,
+    rng: Rng,
+    nodes: Vec<VirtualHc12>,
+    /// Time at which each node finished sending its last packet
+    busy_until_us: Vec<u64>,
+    in_flight: Vec<Packet>,
+    now_us: u64,
+    stats: EtherStats,
+}
+
+impl Default for Ether {
+    fn default() -> Self {
+        Self::new(EtherConfig::default())
+    }
+}
+
+impl Ether {
+    /// Construct an ether without nodes at time 0
+    pub fn new(config: EtherConfig) -> Self {
+        Self {
+            config,
+            rng: Rng::new(config.seed),
+            nodes: Vec::new(),
+            busy_until_us: Vec::new(),
+            in_flight: Vec::new(),
+            now_us: 0,
+            stats: EtherStats::default(),
+        }
+    }
+
+    /// Add a new module with the given parameters and return it
+    pub fn add_node(&mut self, parameters: Parameters) -> VirtualHc12 {
+        let node = VirtualHc12::new(parameters);
+        self.attach(node.clone());
+        node
+    }
+
+    /// Connect an existing module to the ether
+    pub fn attach(&mut self, node: VirtualHc12) {
+        self.nodes.push(node);
+        self.busy_until_us.push(self.now_us);
+    }
+
+    /// Current time of the virtual clock in microseconds
+    pub fn now_us(&self) -> u64 {
+        self.now_us
+    }
+
+    /// Counters of what happened so far
+    pub fn stats(&self) -> EtherStats {
+        self.stats
+    }
+
+    /// Whether no packet is on the air
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    /// Put the bytes written by the nodes on the air, move the clock forward and deliver the packets which landed
+    pub fn advance(&mut self, duration_us: u64) {
+        self.collect();
+        self.now_us += duration_us;
+        self.deliver();
+    }
+
+    /// Put the bytes written by the nodes on the air and advance until all packets landed
+    pub fn flush(&mut self) {
+        self.collect();
+        let end = self.in_flight.iter().map(|packet| packet.end_us).max();
+        if let Some(end) = end {
+            self.now_us = self.now_us.max(end);
+        }
+        self.deliver();
+    }
+
+    /// Turn bytes written by the nodes into packets on the air
+    fn collect(&mut self) {
+        for sender in 0..self.nodes.len() {
+            let bytes = self.nodes[sender].take_transmitted();
+            if bytes.is_empty() {
+                continue;
+            }
+            let params = self.nodes[sender].parameters();
+            let link = Link::from(&params);
+            let chunk_len = params.max_packet_len().unwrap_or(bytes.len());
+            for chunk in bytes.chunks(chunk_len) {
+                let start_us = self.busy_until_us[sender].max(self.now_us);
+                let end_us = start_us + airtime_us(chunk.len(), link.air_baud_rate);
+                self.busy_until_us[sender] = end_us;
+                self.send(Packet {
+                    sender,
+                    link,
+                    start_us,
+                    end_us,
+                    bytes: chunk.to_vec(),
+                    collided: false,
+                });
+            }
+        }
+    }
+
+    /// Put a packet on the air, destroying it and every overlapping packet of another node on the same link
+    fn send(&mut self, mut packet: Packet) {
+        self.stats.sent += 1;
+        for other in &mut self.in_flight {
+            let overlaps = other.start_us < packet.end_us && packet.start_us < other.end_us;
+            if other.sender != packet.sender && other.link == packet.link && overlaps {
+                other.collided = true;
+                packet.collided = true;
+            }
+        }
+        self.in_flight.push(packet);
+    }
+
+    /// Hand the packets which landed by now to their receivers, in the order they landed
+    fn deliver(&mut self) {
+        let now_us = self.now_us;
+        let (mut landed, in_flight): (Vec<_>, Vec<_>) = self
+            .in_flight
+            .drain(..)
+            .partition(|packet| packet.end_us <= now_us);
+        self.in_flight = in_flight;
+        landed.sort_by_key(|packet| packet.end_us);
+        for packet in landed {
+            if packet.collided {
+                self.stats.collided += 1;
+                continue;
+            }
+            for receiver in 0..self.nodes.len() {
+                if receiver == packet.sender
+                    || Link::from(&self.nodes[receiver].parameters()) != packet.link
+                {
+                    continue;
+                }
+                if self.rng.chance(self.config.packet_loss) {
+                    self.stats.lost += 1;
+                    continue;
+                }
+                let mut bytes = packet.bytes.clone();
+                for byte in &mut bytes {
+                    for bit in 0..8 {
+                        if self.rng.chance(self.config.bit_error_rate) {
+                            *byte ^= 1 << bit;
+                        }
+                    }
+                }
+                if self.nodes[receiver].receive(&bytes) {
+                    self.stats.delivered += 1;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Virtual Hc12 to test complete driver flows without hardware.
+//!
+//! `VirtualHc12` keeps the state of a module: the persisted parameters, the level of the SET pin and whether it sleeps.
+//! Its serial port and SET pin are handed out as separate handles which share that state,
+//! so they can be passed to `Hc12::new` like the peripherals of a real module.
+//! While SET is low, complete lines written to the serial port are answered like the firmware answers AT commands.
+//! Power can be switched with the handle from `VirtualHc12::power_pin`; with SET low at power-up, the module talks at 9600 bps.
+//! While SET is high, written bytes are transmitted over the air, see `VirtualHc12::take_transmitted` and `VirtualHc12::receive`.
+//! `ether::Ether` connects several modules by radio.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+
+use crate::hc12::baud_rate::ReconfigureBaudRate;
+use crate::settings::parameter::{baudrate::BaudRate, parameters::Parameters};
+use crate::settings::protocol::{Command, Response};
+use crate::RawResponse;
+
+pub mod ether;
+
+#[cfg(test)]
+mod test;
+
+/// Version reported by a virtual Hc12 unless configured otherwise
+pub const DEFAULT_VERSION: &[u8] = b"www.hc01.com HC-12_V2.6";
+
+/// Answer of the firmware to a command it does not understand or cannot apply
+const ERROR_RESPONSE: &[u8] = b"ERROR\r\n";
+
+/// Longest command line the firmware buffers, longer lines are dropped
+const MAX_COMMAND_LEN: usize = 16;
+
+/// State shared by the handles of a virtual Hc12
+#[derive(Debug)]
+struct State {
+    /// Persisted parameters, reset to factory defaults by "AT+DEFAULT"
+    parameters: Parameters,
+    version: RawResponse,
+    /// Whether power is applied
+    powered: bool,
+    /// Whether SET was low at power-up, so the serial port runs at 9600 bps until SET goes high
+    factory_baud_rate: bool,
+    /// Whether "AT+Bxxx" is acknowledged without changing the baud rate
+    fixed_baud_rate: bool,
+    /// Whether SET is high
+    set_high: bool,
+    /// Whether "AT+SLEEP" was acknowledged, so the module sleeps once SET goes high
+    sleep_pending: bool,
+    sleeping: bool,
+    /// Baud rate of the host side of the serial connection
+    host_baud_rate: BaudRate,
+    /// Partial command line received in config mode
+    command: Vec<u8>,
+    /// Bytes waiting to be read by the host
+    to_host: VecDeque<u8>,
+    /// Bytes the host wrote in transparent mode
+    transmitted: Vec<u8>,
+}
+
+impl State {
+    /// Whether the serial settings of host and module agree, so bytes pass between them
+    fn connected(&self) -> bool {
+        let baud_rate = if self.factory_baud_rate {
+            BaudRate::Bps9600
+        } else {
+            self.parameters.baud_rate
+        };
+        self.powered && self.host_baud_rate == baud_rate
+    }
+
+    /// Handle a byte written by the host
+    fn accept(&mut self, byte: u8) {
+        if !self.connected() || self.sleeping {
+            return;
+        }
+        if self.set_high {
+            self.transmitted.push(byte);
+            return;
+        }
+        self.command.push(byte);
+        if byte == b'\n' {
+            let line = std::mem::take(&mut self.command);
+            self.execute(&line);
+        } else if self.command.len() > MAX_COMMAND_LEN {
+            self.command.clear();
+        }
+    }
+
+    /// Answer a complete command line
+    fn execute(&mut self, line: &[u8]) {
+        let command = match Command::decode(line) {
+            Ok(command) => command,
+            Err(_) => return self.respond(ERROR_RESPONSE),
+        };
+        let mut buffer = [0u8; 34];
+        match command {
+            Command::QueryParameters => {
+                let params = self.parameters;
+                for response in &[
+                    Response::BaudRate(params.baud_rate),
+                    Response::Channel(params.channel),
+                    Response::Power(params.power),
+                    Response::Mode(params.mode),
+                ] {
+                    self.respond(response.encode(&mut buffer));
+                }
+            }
+            command => match self.apply(command) {
+                // The answer goes out before a new baud rate takes effect
+                Some((response, parameters)) => {
+                    self.respond(response.encode(&mut buffer));
+                    self.parameters = parameters;
+                }
+                None => self.respond(ERROR_RESPONSE),
+            },
+        }
+    }
+
+    /// Response to a single command and the parameters after it, or `None` if the firmware rejects it
+    fn apply(&mut self, command: Command) -> Option<(Response, Parameters)> {
+        let mut params = self.parameters;
+        let response = match command {
+            Command::Test => Response::Ok,
+            Command::SetBaudRate(rate) => {
+                params.set_baud_rate(rate).ok()?;
+                if self.fixed_baud_rate {
+                    params.baud_rate = self.parameters.baud_rate;
+                }
+                Response::BaudRate(rate)
+            }
+            Command::SetChannel(channel) => {
+                params.channel = channel;
+                Response::ChannelSet(channel)
+            }
+            Command::SetMode(mode) => {
+                params.mode = mode;
+                params.validate().ok()?;
+                Response::Mode(mode)
+            }
+            Command::SetPower(power) => {
+                params.power = power;
+                Response::PowerSet(power)
+            }
+            Command::SetSerialFormat(format) => {
+                params.serial_format = format;
+                Response::SerialFormat(format)
+            }
+            Command::QueryBaudRate => Response::BaudRate(params.baud_rate),
+            Command::QueryChannel => Response::Channel(params.channel),
+            Command::QueryMode => Response::Mode(params.mode),
+            Command::QueryPower => Response::Power(params.power),
+            Command::QuerySerialFormat => Response::SerialFormat(params.serial_format),
+            Command::QueryVersion => Response::Version(self.version.clone()),
+            Command::Sleep => {
+                self.sleep_pending = true;
+                Response::Sleep
+            }
+            Command::Default => {
+                params = Parameters::default();
+                Response::Default
+            }
+            Command::QueryParameters => return None,
+        };
+        Some((response, params))
+    }
+
+    /// Queue bytes for the host
+    fn respond(&mut self, bytes: &[u8]) {
+        if self.connected() {
+            self.to_host.extend(bytes);
+        }
+    }
+
+    /// Change the level of SET
+    fn set_pin(&mut self, high: bool) {
+        if high == self.set_high {
+            return;
+        }
+        self.set_high = high;
+        self.command.clear();
+        if high {
+            self.factory_baud_rate = false;
+            self.sleeping = self.sleep_pending;
+            self.sleep_pending = false;
+        } else {
+            // Pulling SET low wakes Hc12 up
+            self.sleeping = false;
+        }
+    }
+
+    /// Switch power, which drops everything but the persisted parameters
+    fn set_power(&mut self, on: bool) {
+        if on && !self.powered {
+            self.factory_baud_rate = !self.set_high;
+        }
+        self.powered = on;
+        self.sleep_pending = false;
+        self.sleeping = false;
+        self.command.clear();
+        self.to_host.clear();
+    }
+}
+
+/// Shared state and a signal for readers waiting on it
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    readable: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panic while holding the lock cannot leave the state half-updated in a way that matters for a simulation
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Virtual Hc12 module
+#[derive(Debug, Clone)]
+pub struct VirtualHc12 {
+    shared: Arc<Shared>,
+}
+
+impl Default for VirtualHc12 {
+    fn default() -> Self {
+        Self::new(Parameters::default())
+    }
+}
+
+impl VirtualHc12 {
+    /// Construct a module with the given persisted parameters, SET high and awake.
+    /// The host side of the serial connection starts at the baud rate of the module.
+    pub fn new(parameters: Parameters) -> Self {
+        let state = State {
+            parameters,
+            version: RawResponse::new(DEFAULT_VERSION),
+            powered: true,
+            factory_baud_rate: false,
+            fixed_baud_rate: false,
+            set_high: true,
+            sleep_pending: false,
+            sleeping: false,
+            host_baud_rate: parameters.baud_rate,
+            command: Vec::new(),
+            to_host: VecDeque::new(),
+            transmitted: Vec::new(),
+        };
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(state),
+                readable: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Set the version line the module reports on "AT+V", without line ending
+    pub fn with_version(self, version: &[u8]) -> Self {
+        self.shared.lock().version = RawResponse::new(version);
+        self
+    }
+
+    /// Make the module acknowledge "AT+Bxxx" but keep its baud rate, like faulty firmware
+    pub fn with_fixed_baud_rate(self) -> Self {
+        self.shared.lock().fixed_baud_rate = true;
+        self
+    }
+
+    /// Serial port connected to the module
+    pub fn serial(&self) -> SimSerial {
+        SimSerial {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// SET pin of the module
+    pub fn set_pin(&self) -> SimSetPin {
+        SimSetPin {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Power switch of the module, which is powered while high
+    pub fn power_pin(&self) -> SimPowerPin {
+        SimPowerPin {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Whether power is applied
+    pub fn is_powered(&self) -> bool {
+        self.shared.lock().powered
+    }
+
+    /// Persisted parameters
+    pub fn parameters(&self) -> Parameters {
+        self.shared.lock().parameters
+    }
+
+    /// Whether SET is low, so the module answers AT commands
+    pub fn is_configuration_mode(&self) -> bool {
+        !self.shared.lock().set_high
+    }
+
+    /// Whether the module sleeps
+    pub fn is_sleeping(&self) -> bool {
+        self.shared.lock().sleeping
+    }
+
+    /// Take the bytes the host wrote in transparent mode
+    pub fn take_transmitted(&self) -> Vec<u8> {
+        std::mem::take(&mut self.shared.lock().transmitted)
+    }
+
+    /// Deliver bytes received over the air to the host.
+    /// They are dropped in config mode and while sleeping, like on a real module.
+    /// Returns whether the module was listening.
+    pub fn receive(&self, bytes: &[u8]) -> bool {
+        let mut state = self.shared.lock();
+        let listening = state.powered && state.set_high && !state.sleeping;
+        if listening {
+            state.respond(bytes);
+            self.shared.readable.notify_all();
+        }
+        listening
+    }
+}
+
+/// Serial port connected to a `VirtualHc12`.
+///
+/// Bytes only pass while the baud rate of the port matches the baud rate of the module.
+#[derive(Debug, Clone)]
+pub struct SimSerial {
+    shared: Arc<Shared>,
+}
+
+impl ErrorType for SimSerial {
+    type Error = Infallible;
+}
+
+impl ReadReady for SimSerial {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.shared.lock().to_host.is_empty())
+    }
+}
+
+impl Read for SimSerial {
+    /// Blocks until the module sent at least one byte, like a real serial port
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.shared.lock();
+        while state.to_host.is_empty() {
+            state = self
+                .shared
+                .readable
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        let count = buf.len().min(state.to_host.len());
+        for (slot, byte) in buf.iter_mut().zip(state.to_host.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for SimSerial {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut state = self.shared.lock();
+        for byte in buf {
+            state.accept(*byte);
+        }
+        if !state.to_host.is_empty() {
+            self.shared.readable.notify_all();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ReconfigureBaudRate for SimSerial {
+    fn set_baud_rate(&mut self, rate: BaudRate) -> Result<(), Self::Error> {
+        self.shared.lock().host_baud_rate = rate;
+        Ok(())
+    }
+}
+
+/// SET pin of a `VirtualHc12`
+#[derive(Debug, Clone)]
+pub struct SimSetPin {
+    shared: Arc<Shared>,
+}
+
+impl PinErrorType for SimSetPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimSetPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.shared.lock().set_pin(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.shared.lock().set_pin(true);
+        Ok(())
+    }
+}
+
+/// Power switch of a `VirtualHc12`
+#[derive(Debug, Clone)]
+pub struct SimPowerPin {
+    shared: Arc<Shared>,
+}
+
+impl PinErrorType for SimPowerPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimPowerPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.shared.lock().set_power(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.shared.lock().set_power(true);
+        Ok(())
+    }
+}
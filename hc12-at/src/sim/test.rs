@@ -0,0 +1,539 @@
+use crate::hc12::any::AnyHc12;
+use crate::hc12::apply::Setting;
+use crate::hc12::baud_rate::ReconfigureBaudRate;
+use crate::hc12::recover::Recovery;
+use crate::hc12::scoped::ConfigurationError;
+use crate::hc12::timing::Timing;
+use crate::hc12::Hc12;
+use crate::hc12::ModeKind;
+use crate::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, transmission_power::TransmissionPower,
+};
+
+use super::ether::{Ether, EtherConfig, EtherStats};
+use super::*;
+
+use debugless_unwrap::{DebuglessUnwrap, DebuglessUnwrapErr};
+use embedded_hal_mock::eh1::delay::NoopDelay as MockNoop;
+
+#[test]
+fn configure_end_to_end() {
+    let module = VirtualHc12::default();
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert!(module.is_configuration_mode());
+
+    hc12.is_ok().unwrap();
+    let version = hc12.get_firmware_version().unwrap();
+    assert_eq!(version.model(), b"HC-12");
+    assert_eq!((version.major, version.minor), (2, 6));
+    assert_eq!(hc12.get_parameters().unwrap(), Parameters::default());
+
+    let target = Parameters {
+        channel: Channel::new(21).unwrap(),
+        power: TransmissionPower::Four,
+        mode: Mode::Fu1,
+        ..Parameters::default()
+    };
+    hc12.apply(&target).debugless_unwrap();
+    assert_eq!(hc12.get_parameters().unwrap(), target);
+    assert_eq!(*hc12.known_parameters(), target);
+
+    let _hc12 = hc12.into_normal_mode().debugless_unwrap();
+    assert!(!module.is_configuration_mode());
+    assert_eq!(module.parameters(), target);
+    assert!(module.take_transmitted().is_empty());
+}
+
+#[test]
+fn reset_settings() {
+    let params = Parameters {
+        channel: Channel::new(100).unwrap(),
+        mode: Mode::Fu2,
+        baud_rate: BaudRate::Bps2400,
+        ..Parameters::default()
+    };
+    let module = VirtualHc12::new(params);
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert_eq!(hc12.get_parameters().unwrap(), params);
+    hc12.reset_settings().unwrap();
+    assert_eq!(module.parameters(), Parameters::default());
+}
+
+#[test]
+fn rejects_invalid_commands() {
+    let module = VirtualHc12::default();
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    // FU2 does not support 9600 bps
+    let err = hc12.set_mode(Mode::Fu2).unwrap_err();
+    assert!(matches!(err, crate::Error::UnexpectedResponse(_)));
+    assert_eq!(module.parameters(), Parameters::default());
+
+    hc12.is_ok().unwrap();
+
+    // A second handle talks to the same module, which is still in config mode
+    let mut serial = module.serial();
+    serial.write_all(b"AT+XYZ\r\n").unwrap();
+    let mut buffer = [0u8; 7];
+    serial.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, ERROR_RESPONSE);
+}
+
+#[test]
+fn transparent_mode() {
+    let module = VirtualHc12::default();
+    let mut hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    hc12.write_buffer(b"AT+B1200\r\n").unwrap();
+    assert_eq!(module.take_transmitted(), b"AT+B1200\r\n");
+    assert_eq!(module.parameters(), Parameters::default());
+
+    assert!(module.receive(b"reply"));
+    let mut buffer = [0u8; 8];
+    let count = hc12.read_buffer_timeout(&mut buffer, 10).unwrap();
+    assert_eq!(&buffer[..count], b"reply");
+}
+
+#[test]
+fn sleep_and_wake_up() {
+    let module = VirtualHc12::default();
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let hc12 = hc12.into_sleeping_mode().debugless_unwrap();
+    assert!(module.is_sleeping());
+    assert!(!module.receive(b"lost"));
+
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    assert!(!module.is_sleeping());
+    hc12.is_ok().unwrap();
+
+    let mut hc12 = hc12.into_normal_mode().debugless_unwrap();
+    assert!(!module.is_sleeping());
+    hc12.write_buffer(b"awake").unwrap();
+    assert_eq!(module.take_transmitted(), b"awake");
+}
+
+#[test]
+fn probe_and_change_baud_rate() {
+    let module = VirtualHc12::new(Parameters {
+        baud_rate: BaudRate::Bps38400,
+        ..Parameters::default()
+    });
+    let mut serial = module.serial();
+    serial.set_baud_rate(BaudRate::Bps9600).unwrap();
+    let (mut hc12, rate) = Hc12::probe(serial, module.set_pin(), MockNoop).debugless_unwrap();
+    assert_eq!(rate, BaudRate::Bps38400);
+
+    hc12.change_baud_rate(BaudRate::Bps115200).unwrap();
+    assert_eq!(module.parameters().baud_rate, BaudRate::Bps115200);
+    hc12.is_ok().unwrap();
+
+    // Without retuning the host, Hc12 is out of reach after changing its baud rate
+    hc12.set_baud_rate(BaudRate::Bps1200).unwrap();
+    hc12.set_timeout_ms(1);
+    let err = hc12.is_ok().unwrap_err();
+    assert!(matches!(err, crate::Error::NoResponse));
+}
+
+#[test]
+fn change_baud_rate_ignored() {
+    let module = VirtualHc12::default().with_fixed_baud_rate();
+    let hc12 = driver(&module);
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.set_timeout_ms(1);
+    let err = hc12.change_baud_rate(BaudRate::Bps19200).unwrap_err();
+    assert!(matches!(err, crate::Error::NoResponse));
+    assert_eq!(module.parameters().baud_rate, BaudRate::Bps9600);
+    assert_eq!(hc12.known_parameters().baud_rate, BaudRate::Bps9600);
+    hc12.is_ok().unwrap();
+}
+
+#[test]
+fn apply_baud_rate() {
+    let module = VirtualHc12::default();
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    let target = Parameters {
+        baud_rate: BaudRate::Bps19200,
+        channel: Channel::new(7).unwrap(),
+        ..Parameters::default()
+    };
+    hc12.apply(&target).debugless_unwrap();
+    assert_eq!(module.parameters(), target);
+    hc12.is_ok().unwrap();
+}
+
+#[test]
+fn apply_baud_rate_rollback() {
+    // Firmware of an unknown model, which is not assumed to support FU4
+    let module = VirtualHc12::default().with_version(b"XY-12_V1.0");
+    let hc12 = Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    let mut hc12 = hc12.into_configuration_mode().debugless_unwrap();
+    hc12.get_firmware_version().unwrap();
+    let target = Parameters {
+        baud_rate: BaudRate::Bps1200,
+        mode: Mode::Fu4,
+        ..Parameters::default()
+    };
+    let report = hc12.apply(&target).debugless_unwrap_err();
+    assert_eq!(report.attempted(), &[Setting::BaudRate, Setting::Mode]);
+    assert_eq!(report.failed, Some(Setting::Mode));
+    assert!(matches!(report.error, crate::Error::Unsupported));
+    assert!(report.restored);
+    assert_eq!(module.parameters(), Parameters::default());
+    hc12.is_ok().unwrap();
+}
+
+#[test]
+fn confirm_configuration_mode() {
+    let module = VirtualHc12::default();
+    let mut power_pin = module.power_pin();
+
+    // Radio data which is still waiting at the serial port does not spoil the "OK"
+    assert!(module.receive(b"OK\r\nnoise"));
+    let mut hc12 = driver(&module).into_configuration_mode().debugless_unwrap();
+    assert_eq!(hc12.get_parameters().unwrap(), Parameters::default());
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+
+    // An unpowered module never answers, so the driver comes back in normal mode
+    power_pin.set_low().unwrap();
+    let failed = hc12.into_configuration_mode().debugless_unwrap_err();
+    assert!(matches!(failed.error, crate::Error::NoResponse));
+    assert!(!module.is_configuration_mode());
+
+    power_pin.set_high().unwrap();
+    let mut hc12 = failed.hc12.into_configuration_mode().debugless_unwrap();
+    hc12.is_ok().unwrap();
+}
+
+#[test]
+fn any_hc12() {
+    let module = VirtualHc12::default();
+    let mut hc12 = AnyHc12::new(module.serial(), module.set_pin(), MockNoop).unwrap();
+    assert_eq!(hc12.current_mode(), ModeKind::Normal);
+    assert!(matches!(
+        hc12.get_parameters(),
+        Err(crate::Error::WrongMode(ModeKind::Normal))
+    ));
+
+    let (mut hc12, result) = hc12.into_configuration_mode();
+    result.unwrap();
+    assert!(module.is_configuration_mode());
+    hc12.set_channel(Channel::new(9).unwrap()).unwrap();
+    assert!(matches!(
+        hc12.write_buffer(b"data"),
+        Err(crate::Error::WrongMode(ModeKind::Configuration))
+    ));
+    hc12.configuration()
+        .unwrap()
+        .apply(&Parameters::default())
+        .debugless_unwrap();
+
+    // Sleep mode is entered and left through config mode
+    let (hc12, result) = hc12.into_normal_mode();
+    result.unwrap();
+    let (hc12, result) = hc12.into_sleeping_mode();
+    result.unwrap();
+    assert_eq!(hc12.current_mode(), ModeKind::Sleep);
+    assert!(module.is_sleeping());
+    let (mut hc12, result) = hc12.into_normal_mode();
+    result.unwrap();
+    assert_eq!(hc12.current_mode(), ModeKind::Normal);
+    assert!(!module.is_sleeping());
+    hc12.write_buffer(b"data").unwrap();
+    assert_eq!(module.take_transmitted(), b"data");
+
+    // A failed transition keeps the driver in its mode
+    module.power_pin().set_low().unwrap();
+    let (hc12, result) = hc12.into_configuration_mode();
+    assert!(matches!(result, Err(crate::Error::NoResponse)));
+    assert_eq!(hc12.current_mode(), ModeKind::Normal);
+    module.power_pin().set_high().unwrap();
+    let (mut hc12, result) = hc12.into_configuration_mode();
+    result.unwrap();
+    hc12.is_ok().unwrap();
+}
+
+#[test]
+fn with_configuration() {
+    let module = VirtualHc12::default();
+    let channel = Channel::new(30).unwrap();
+    let (hc12, previous) = driver(&module)
+        .with_configuration(|hc12| {
+            let previous = hc12.get_parameters()?.channel;
+            hc12.set_channel(channel)?;
+            Ok::<_, crate::Error<_, _>>(previous)
+        })
+        .debugless_unwrap();
+    assert_eq!(previous, Channel::default());
+    assert_eq!(module.parameters().channel, channel);
+    assert!(!module.is_configuration_mode());
+
+    // A failed closure still returns to normal mode
+    let err = hc12
+        .with_configuration(|hc12| hc12.set_mode(Mode::Fu2))
+        .debugless_unwrap_err();
+    let hc12 = match err {
+        ConfigurationError::Closure { hc12, error } => {
+            assert!(matches!(error, crate::Error::UnexpectedResponse(_)));
+            hc12
+        }
+        _ => panic!("closure should have failed"),
+    };
+    assert!(!module.is_configuration_mode());
+
+    // Without power, the closure never runs
+    module.power_pin().set_low().unwrap();
+    let mut ran = false;
+    let err = hc12
+        .with_configuration(|hc12| {
+            ran = true;
+            hc12.is_ok()
+        })
+        .debugless_unwrap_err();
+    assert!(!ran);
+    let mut hc12 = match err {
+        ConfigurationError::Enter(failed) => failed.hc12,
+        _ => panic!("config mode should not have been entered"),
+    };
+    module.power_pin().set_high().unwrap();
+    hc12.write_buffer(b"data").unwrap();
+    assert_eq!(module.take_transmitted(), b"data");
+}
+
+/// Module whose baud rate nobody remembers
+fn lost_module() -> VirtualHc12 {
+    VirtualHc12::new(Parameters {
+        baud_rate: BaudRate::Bps38400,
+        channel: Channel::new(42).unwrap(),
+        mode: Mode::Fu1,
+        ..Parameters::default()
+    })
+}
+
+#[test]
+fn recover_with_power_pin() {
+    let module = lost_module();
+    let mut power_pin = module.power_pin();
+    let mut hc12 = Hc12::recover(
+        module.serial(),
+        module.set_pin(),
+        MockNoop,
+        &mut power_pin,
+        Timing::default(),
+        Recovery::Reset,
+    )
+    .debugless_unwrap();
+    assert!(module.is_powered());
+    assert_eq!(module.parameters(), Parameters::default());
+    assert_eq!(hc12.get_parameters().unwrap(), Parameters::default());
+
+    let mut hc12 = hc12.into_normal_mode().debugless_unwrap();
+    hc12.write_buffer(b"hello").unwrap();
+    assert_eq!(module.take_transmitted(), b"hello");
+}
+
+#[test]
+fn recover_by_hand() {
+    let module = lost_module();
+    let mut set_pin = module.set_pin();
+    let mut power_pin = module.power_pin();
+
+    // Without a power cycle, Hc12 keeps its baud rate
+    let err = Hc12::recover_by_hand(
+        module.serial(),
+        module.set_pin(),
+        MockNoop,
+        Timing::default(),
+        Recovery::Keep,
+    )
+    .debugless_unwrap_err();
+    assert!(matches!(err, crate::Error::NoResponse));
+
+    // Power-cycled with SET low, Hc12 talks at 9600 bps until SET goes high
+    power_pin.set_low().unwrap();
+    set_pin.set_low().unwrap();
+    power_pin.set_high().unwrap();
+    let target = Parameters {
+        channel: Channel::new(7).unwrap(),
+        ..lost_module().parameters()
+    };
+    let hc12 = Hc12::recover_by_hand(
+        module.serial(),
+        module.set_pin(),
+        MockNoop,
+        Timing::default(),
+        Recovery::Apply(target),
+    )
+    .debugless_unwrap();
+    assert_eq!(module.parameters(), target);
+
+    let mut hc12 = hc12.into_normal_mode().debugless_unwrap();
+    hc12.write_buffer(b"lost").unwrap();
+    assert!(module.take_transmitted().is_empty());
+}
+
+#[test]
+fn recover_apply_baud_rate() {
+    let module = lost_module();
+    let mut power_pin = module.power_pin();
+    let target = Parameters {
+        baud_rate: BaudRate::Bps19200,
+        channel: Channel::new(5).unwrap(),
+        ..Parameters::default()
+    };
+    let hc12 = Hc12::recover(
+        module.serial(),
+        module.set_pin(),
+        MockNoop,
+        &mut power_pin,
+        Timing::default(),
+        Recovery::Apply(target),
+    )
+    .debugless_unwrap();
+    assert_eq!(module.parameters(), target);
+    assert_eq!(hc12.known_parameters().baud_rate, BaudRate::Bps19200);
+
+    // Hc12 switches to the stored baud rate when SET goes high, and the host follows
+    let hc12 = hc12.into_normal_mode().debugless_unwrap();
+    let (mut serial, set_pin, delay) = hc12.release();
+    serial.set_baud_rate(BaudRate::Bps19200).unwrap();
+    let mut hc12 = Hc12::new(serial, set_pin, delay).unwrap();
+    hc12.write_buffer(b"found").unwrap();
+    assert_eq!(module.take_transmitted(), b"found");
+}
+
+#[test]
+fn recover_keeps_parameters() {
+    let module = lost_module();
+    let mut power_pin = module.power_pin();
+    let mut hc12 = Hc12::recover(
+        module.serial(),
+        module.set_pin(),
+        MockNoop,
+        &mut power_pin,
+        Timing::default(),
+        Recovery::Keep,
+    )
+    .debugless_unwrap();
+    assert_eq!(hc12.get_parameters().unwrap(), lost_module().parameters());
+}
+
+/// Driver in normal mode on a virtual Hc12
+fn driver(module: &VirtualHc12) -> Hc12<SimSerial, SimSetPin, MockNoop, crate::hc12::Normal> {
+    Hc12::new(module.serial(), module.set_pin(), MockNoop).unwrap()
+}
+
+/// Bytes which already arrived at the driver
+fn received(hc12: &mut Hc12<SimSerial, SimSetPin, MockNoop, crate::hc12::Normal>) -> Vec<u8> {
+    let mut buffer = [0u8; 64];
+    let count = hc12.read_buffer_timeout(&mut buffer, 1).unwrap();
+    buffer[..count].to_vec()
+}
+
+#[test]
+fn ether_delivers_on_same_link() {
+    let mut ether = Ether::default();
+    let mut a = driver(&ether.add_node(Parameters::default()));
+    let mut b = driver(&ether.add_node(Parameters::default()));
+    let mut other_channel = driver(&ether.add_node(Parameters {
+        channel: Channel::new(2).unwrap(),
+        ..Parameters::default()
+    }));
+    let mut other_mode = driver(&ether.add_node(Parameters {
+        mode: Mode::Fu1,
+        ..Parameters::default()
+    }));
+    let mut other_air_rate = driver(&ether.add_node(Parameters {
+        baud_rate: BaudRate::Bps19200,
+        ..Parameters::default()
+    }));
+
+    a.write_buffer(b"hello").unwrap();
+    ether.advance(0);
+    assert!(!ether.is_idle());
+    assert!(received(&mut b).is_empty());
+
+    // 5 bytes of 10 bits at 15000 bps in the air
+    ether.advance(3333);
+    assert!(received(&mut b).is_empty());
+    ether.advance(1);
+    assert!(ether.is_idle());
+    assert_eq!(received(&mut b), b"hello");
+    assert!(received(&mut a).is_empty());
+    assert!(received(&mut other_channel).is_empty());
+    assert!(received(&mut other_mode).is_empty());
+    assert!(received(&mut other_air_rate).is_empty());
+    assert_eq!(
+        ether.stats(),
+        EtherStats {
+            sent: 1,
+            delivered: 1,
+            ..EtherStats::default()
+        }
+    );
+}
+
+#[test]
+fn ether_collisions() {
+    let mut ether = Ether::default();
+    let mut a = driver(&ether.add_node(Parameters::default()));
+    let mut b = driver(&ether.add_node(Parameters::default()));
+    let mut c = driver(&ether.add_node(Parameters::default()));
+
+    a.write_buffer(b"from a").unwrap();
+    b.write_buffer(b"from b").unwrap();
+    ether.flush();
+    assert!(received(&mut c).is_empty());
+    assert_eq!(ether.stats().collided, 2);
+
+    // Packets of one node queue up instead of colliding
+    a.write_buffer(b"first").unwrap();
+    ether.advance(0);
+    a.write_buffer(b"second").unwrap();
+    ether.flush();
+    assert_eq!(received(&mut c), b"firstsecond");
+}
+
+#[test]
+fn ether_loss_and_bit_errors() {
+    let mut ether = Ether::new(EtherConfig {
+        packet_loss: 1.0,
+        ..EtherConfig::default()
+    });
+    let mut a = driver(&ether.add_node(Parameters::default()));
+    let mut b = driver(&ether.add_node(Parameters::default()));
+    a.write_buffer(b"lost").unwrap();
+    ether.flush();
+    assert!(received(&mut b).is_empty());
+    assert_eq!(ether.stats().lost, 1);
+
+    let mut ether = Ether::new(EtherConfig {
+        bit_error_rate: 1.0,
+        ..EtherConfig::default()
+    });
+    let mut a = driver(&ether.add_node(Parameters::default()));
+    let mut b = driver(&ether.add_node(Parameters::default()));
+    a.write_buffer(&[0x00, 0x0f]).unwrap();
+    ether.flush();
+    assert_eq!(received(&mut b), [0xff, 0xf0]);
+}
+
+#[test]
+fn ether_fu4_packets() {
+    let fu4 = Parameters {
+        baud_rate: BaudRate::Bps1200,
+        mode: Mode::Fu4,
+        ..Parameters::default()
+    };
+    let mut ether = Ether::default();
+    let mut a = driver(&ether.add_node(fu4));
+    let mut b = driver(&ether.add_node(fu4));
+    a.write_buffer(&[b'x'; 61]).unwrap();
+    ether.flush();
+    assert_eq!(ether.stats().sent, 2);
+    // 61 bytes of 10 bits at 500 bps in the air
+    assert_eq!(ether.now_us(), 1_220_000);
+    assert_eq!(received(&mut b).len(), 61);
+}
@@ -0,0 +1,273 @@
+//! Command-line tool to configure HC-12 modules from a Linux host.
+//!
+//! The module is connected through a USB-serial adapter.
+//! Its SET pin is driven by the RTS or DTR line of the adapter, or wired by hand:
+//! tied low for configuration commands and left open for `send` and `listen`.
+
+#![deny(missing_docs)]
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand};
+use hc12_at::hc12::{Configuration, Hc12, Normal};
+use hc12_at::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, parameters::Parameters,
+    transmission_power::TransmissionPower,
+};
+use serialport::TTYPort;
+
+pub mod output;
+pub mod port;
+
+use output::Format;
+use port::{Delay, SetPin, Wiring};
+
+/// Hc12 on the serial port of the host
+type Driver<M> = Hc12<port::Port, SetPin, Delay, M>;
+
+/// Arguments of the command-line tool
+#[derive(Debug, Parser)]
+#[command(
+    name = "hc12",
+    version,
+    about = "Configure HC-12 modules and talk through them"
+)]
+pub struct Cli {
+    /// Serial port the module is connected to
+    #[arg(short, long, default_value = "/dev/ttyUSB0")]
+    pub port: String,
+    /// Baud rate of the serial port
+    #[arg(long, default_value = "9600", value_parser = parse_baud_rate)]
+    pub port_baud: BaudRate,
+    /// How SET is connected
+    #[arg(long, value_enum, default_value_t = Wiring::Manual)]
+    pub set_pin: Wiring,
+    /// SET is connected through an inverting stage
+    #[arg(long)]
+    pub invert_set: bool,
+    /// Milliseconds to wait for an answer of the module
+    #[arg(long, default_value_t = 1000)]
+    pub timeout_ms: u16,
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json: bool,
+    /// Command to run
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Commands of the command-line tool
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print firmware version and parameters (SET low)
+    Info,
+    /// Change parameters, keeping the others (SET low)
+    Set {
+        /// Channel from 1 to 127
+        #[arg(long, value_parser = parse_channel)]
+        channel: Option<Channel>,
+        /// Baud rate of the module, the serial port follows it
+        #[arg(long, value_parser = parse_baud_rate)]
+        baud: Option<BaudRate>,
+        /// Transmission power level from 1 to 8
+        #[arg(long, value_parser = parse_power)]
+        power: Option<TransmissionPower>,
+        /// Mode, like "FU3" or "3"
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<Mode>,
+    },
+    /// Reset parameters to factory defaults (SET low)
+    Reset,
+    /// Put the module to sleep once SET is released (SET low)
+    Sleep,
+    /// Find the baud rate of the module (SET low)
+    Probe,
+    /// Send data over the air, from the argument or standard input (SET open)
+    Send {
+        /// Data to send
+        data: Option<String>,
+    },
+    /// Print data received over the air (SET open)
+    Listen {
+        /// Stop after this many milliseconds instead of running until interrupted
+        #[arg(long)]
+        duration_ms: Option<u64>,
+    },
+}
+
+fn parse_baud_rate(value: &str) -> Result<BaudRate, String> {
+    value
+        .parse::<i32>()
+        .ok()
+        .and_then(|rate| BaudRate::try_from(rate).ok())
+        .ok_or_else(|| format!("{} is not a baud rate of HC-12", value))
+}
+
+fn parse_channel(value: &str) -> Result<Channel, String> {
+    value
+        .parse::<u8>()
+        .ok()
+        .and_then(Channel::new)
+        .ok_or_else(|| format!("{} is not a channel from 1 to 127", value))
+}
+
+fn parse_power(value: &str) -> Result<TransmissionPower, String> {
+    value
+        .parse::<u8>()
+        .ok()
+        .and_then(TransmissionPower::new)
+        .ok_or_else(|| format!("{} is not a power level from 1 to 8", value))
+}
+
+fn parse_mode(value: &str) -> Result<Mode, String> {
+    let upper = value.to_ascii_uppercase();
+    upper
+        .strip_prefix("FU")
+        .unwrap_or(&upper)
+        .parse::<u8>()
+        .ok()
+        .and_then(Mode::new)
+        .ok_or_else(|| format!("{} is not a mode from FU1 to FU4", value))
+}
+
+/// Error of the command-line tool
+#[derive(Debug)]
+pub enum CliError {
+    /// Opening the serial port failed
+    Open(serialport::Error),
+    /// Reading the input or writing the output failed
+    Io(io::Error),
+    /// Hc12 did not answer as expected
+    Hc12(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Open(e) => write!(f, "cannot open serial port: {}", e),
+            CliError::Io(e) => write!(f, "{}", e),
+            CliError::Hc12(e) => write!(f, "hc12: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+/// Driver errors only implement `Debug`, as the driver is `no_std`
+fn hc12_error<E: fmt::Debug>(e: E) -> CliError {
+    CliError::Hc12(format!("{:?}", e))
+}
+
+/// Run the command on the module at `port`, reading data to send from `input`
+pub fn run(
+    cli: &Cli,
+    port: TTYPort,
+    input: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+) -> Result<(), CliError> {
+    let format = if cli.json {
+        Format::Json
+    } else {
+        Format::Human
+    };
+    match &cli.command {
+        Command::Info => {
+            let mut hc12 = configure(cli, port)?;
+            let version = hc12.get_firmware_version().map_err(hc12_error)?;
+            let params = hc12.get_parameters().map_err(hc12_error)?;
+            output::info(out, format, &version, &params)?;
+        }
+        Command::Set {
+            channel,
+            baud,
+            power,
+            mode,
+        } => {
+            let mut hc12 = configure(cli, port)?;
+            let current = hc12.get_parameters().map_err(hc12_error)?;
+            let target = Parameters {
+                channel: channel.unwrap_or(current.channel),
+                baud_rate: baud.unwrap_or(current.baud_rate),
+                power: power.unwrap_or(current.power),
+                mode: mode.unwrap_or(current.mode),
+                ..current
+            };
+            set(&mut hc12, &target)?;
+            let params = hc12.get_parameters().map_err(hc12_error)?;
+            output::parameters(out, format, &params)?;
+        }
+        Command::Reset => {
+            let mut hc12 = configure(cli, port)?;
+            hc12.reset_settings().map_err(hc12_error)?;
+            output::status(out, format, "reset to factory defaults")?;
+        }
+        Command::Sleep => {
+            let hc12 = configure(cli, port)?;
+            hc12.into_sleeping_mode()
+                .map_err(|failed| hc12_error(failed.error))?;
+            output::status(out, format, "sleeping once SET is released")?;
+        }
+        Command::Probe => {
+            let (serial, set_pin) = port::connect(port, cli.set_pin, cli.invert_set);
+            let (_, rate) = Hc12::probe(serial, set_pin, Delay).map_err(hc12_error)?;
+            output::baud_rate(out, format, rate)?;
+        }
+        Command::Send { data } => {
+            let data = match data {
+                Some(data) => data.clone().into_bytes(),
+                None => {
+                    let mut data = Vec::new();
+                    input.read_to_end(&mut data)?;
+                    data
+                }
+            };
+            let mut hc12 = transparent(cli, port)?;
+            hc12.write_buffer(&data).map_err(hc12_error)?;
+            output::status(out, format, &format!("sent {} bytes", data.len()))?;
+        }
+        Command::Listen { duration_ms } => {
+            let mut hc12 = transparent(cli, port)?;
+            let start = Instant::now();
+            let mut buffer = [0u8; 64];
+            while duration_ms.is_none_or(|ms| start.elapsed() < Duration::from_millis(ms)) {
+                let count = hc12
+                    .read_buffer_timeout(&mut buffer, 10)
+                    .map_err(hc12_error)?;
+                if count > 0 {
+                    output::received(out, format, &buffer[..count])?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hc12 in normal mode, for transparent transmission
+fn transparent(cli: &Cli, port: TTYPort) -> Result<Driver<Normal>, CliError> {
+    let (serial, set_pin) = port::connect(port, cli.set_pin, cli.invert_set);
+    let mut hc12 = Hc12::new(serial, set_pin, Delay).map_err(hc12_error)?;
+    hc12.set_timeout_ms(cli.timeout_ms);
+    Ok(hc12)
+}
+
+/// Hc12 in config mode, for AT commands
+fn configure(cli: &Cli, port: TTYPort) -> Result<Driver<Configuration>, CliError> {
+    transparent(cli, port)?
+        .into_configuration_mode()
+        .map_err(|failed| hc12_error(failed.error))
+}
+
+/// Apply the target parameters; `Hc12::apply` moves the serial port along with the baud rate of the module.
+fn set(hc12: &mut Driver<Configuration>, target: &Parameters) -> Result<(), CliError> {
+    hc12.apply(target)
+        .map_err(|report| hc12_error(report.error))
+}
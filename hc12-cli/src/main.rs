@@ -0,0 +1,24 @@
+use std::io;
+use std::process;
+
+use clap::Parser;
+use hc12_cli::port;
+use hc12_cli::{run, Cli, CliError};
+
+fn main() {
+    let cli = Cli::parse();
+    let result = port::open(&cli.port, cli.port_baud)
+        .map_err(CliError::Open)
+        .and_then(|port| {
+            run(
+                &cli,
+                port,
+                &mut io::stdin().lock(),
+                &mut io::stdout().lock(),
+            )
+        });
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
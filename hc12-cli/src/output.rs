@@ -0,0 +1,143 @@
+//! Human-readable and JSON output of the commands.
+
+use std::io::{self, Write};
+
+use hc12_at::settings::parameter::{
+    baudrate::BaudRate, mode::Mode, parameters::Parameters, serial_format::SerialFormat,
+};
+use hc12_at::settings::version::FirmwareVersion;
+use num_traits::ToPrimitive;
+use serde_json::{json, Value};
+
+/// Format of the output
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned lines of text
+    Human,
+    /// One JSON value per line
+    Json,
+}
+
+/// Name of the mode, like "FU3"
+fn mode_name(mode: Mode) -> String {
+    format!("FU{}", mode.number())
+}
+
+/// Serial format like "8N1"
+fn format_name(format: &SerialFormat) -> String {
+    String::from_utf8_lossy(&<[u8; 3]>::from(format)).into_owned()
+}
+
+fn parameters_json(params: &Parameters) -> Value {
+    json!({
+        "baud_rate": params.baud_rate.to_u32(),
+        "channel": params.channel.to_u8(),
+        "frequency_mhz": params.channel.get_freq_mhz(),
+        "power": params.power.to_u8(),
+        "power_dbm": params.power.get_power_dbm(),
+        "mode": mode_name(params.mode),
+        "serial_format": format_name(&params.serial_format),
+        "air_baud_rate": params.get_air_baud_rate().to_u32(),
+    })
+}
+
+fn write_parameters(out: &mut dyn Write, params: &Parameters) -> io::Result<()> {
+    writeln!(
+        out,
+        "baud rate:     {} bps",
+        params.baud_rate.to_u32().unwrap_or_default()
+    )?;
+    writeln!(
+        out,
+        "channel:       {} ({:.1} MHz)",
+        params.channel.to_u8().unwrap_or_default(),
+        params.channel.get_freq_mhz()
+    )?;
+    writeln!(
+        out,
+        "power:         {} ({} dBm)",
+        params.power.to_u8().unwrap_or_default(),
+        params.power.get_power_dbm()
+    )?;
+    writeln!(out, "mode:          {}", mode_name(params.mode))?;
+    writeln!(out, "serial format: {}", format_name(&params.serial_format))?;
+    writeln!(
+        out,
+        "air baud rate: {} bps",
+        params.get_air_baud_rate().to_u32().unwrap_or_default()
+    )
+}
+
+/// Firmware version and parameters
+pub fn info(
+    out: &mut dyn Write,
+    format: Format,
+    version: &FirmwareVersion,
+    params: &Parameters,
+) -> io::Result<()> {
+    let vendor = String::from_utf8_lossy(version.vendor());
+    let model = String::from_utf8_lossy(version.model());
+    match format {
+        Format::Human => {
+            writeln!(
+                out,
+                "firmware:      {} {} V{}.{}",
+                vendor, model, version.major, version.minor
+            )?;
+            write_parameters(out, params)
+        }
+        Format::Json => {
+            let value = json!({
+                "firmware": {
+                    "vendor": vendor,
+                    "model": model,
+                    "major": version.major,
+                    "minor": version.minor,
+                },
+                "parameters": parameters_json(params),
+            });
+            writeln!(out, "{}", value)
+        }
+    }
+}
+
+/// Parameters after a change
+pub fn parameters(out: &mut dyn Write, format: Format, params: &Parameters) -> io::Result<()> {
+    match format {
+        Format::Human => write_parameters(out, params),
+        Format::Json => writeln!(out, "{}", json!({ "parameters": parameters_json(params) })),
+    }
+}
+
+/// Baud rate found by probing
+pub fn baud_rate(out: &mut dyn Write, format: Format, rate: BaudRate) -> io::Result<()> {
+    let rate = rate.to_u32().unwrap_or_default();
+    match format {
+        Format::Human => writeln!(out, "baud rate:     {} bps", rate),
+        Format::Json => writeln!(out, "{}", json!({ "baud_rate": rate })),
+    }
+}
+
+/// Outcome of a command without further data
+pub fn status(out: &mut dyn Write, format: Format, message: &str) -> io::Result<()> {
+    match format {
+        Format::Human => writeln!(out, "{}", message),
+        Format::Json => writeln!(out, "{}", json!({ "status": message })),
+    }
+}
+
+/// Data received in transparent mode, raw for humans and as one JSON object per chunk
+pub fn received(out: &mut dyn Write, format: Format, data: &[u8]) -> io::Result<()> {
+    match format {
+        Format::Human => out.write_all(data)?,
+        Format::Json => {
+            let hex: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+            writeln!(
+                out,
+                "{}",
+                json!({ "data": String::from_utf8_lossy(data), "hex": hex })
+            )?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,84 @@
+//! Host resources for the driver: serial port, SET pin and delay.
+
+use std::thread;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+use hc12_at::serial_port::{ControlLine, ModemLineError, ModemLinePin, SharedSerial};
+use hc12_at::settings::parameter::baudrate::BaudRate;
+use num_traits::ToPrimitive;
+use serialport::TTYPort;
+
+/// Serial port of the host
+pub type Port = SharedSerial<TTYPort>;
+
+/// Open the serial port at `path` with 8N1 at the given baud rate
+pub fn open(path: &str, rate: BaudRate) -> serialport::Result<TTYPort> {
+    serialport::new(path, rate.to_u32().unwrap_or(9600))
+        .timeout(Duration::from_millis(100))
+        .open_native()
+}
+
+/// How SET is connected to the host
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Wiring {
+    /// Wired by hand: tied low for configuration commands and left open for transparent mode
+    Manual,
+    /// RTS line of the adapter
+    Rts,
+    /// DTR line of the adapter
+    Dtr,
+}
+
+/// SET pin, driven by a control line of the serial port or by hand
+#[derive(Debug)]
+pub enum SetPin {
+    /// Wired by hand, so setting it does nothing
+    Manual,
+    /// Control line of the serial port
+    Line(ModemLinePin<TTYPort>),
+}
+
+/// Share the serial port with the SET pin as wired
+pub fn connect(port: TTYPort, wiring: Wiring, inverted: bool) -> (Port, SetPin) {
+    let serial = SharedSerial::new(port);
+    let line = match wiring {
+        Wiring::Manual => return (serial, SetPin::Manual),
+        Wiring::Rts => ControlLine::Rts,
+        Wiring::Dtr => ControlLine::Dtr,
+    };
+    let set_pin = serial.set_pin(line, inverted);
+    (serial, SetPin::Line(set_pin))
+}
+
+impl PinErrorType for SetPin {
+    type Error = ModemLineError;
+}
+
+impl OutputPin for SetPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        match self {
+            SetPin::Manual => Ok(()),
+            SetPin::Line(pin) => pin.set_low(),
+        }
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        match self {
+            SetPin::Manual => Ok(()),
+            SetPin::Line(pin) => pin.set_high(),
+        }
+    }
+}
+
+/// Delay which puts the thread to sleep
+#[derive(Debug, Default)]
+pub struct Delay;
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        thread::sleep(Duration::from_nanos(u64::from(ns)));
+    }
+}
@@ -0,0 +1,222 @@
+//! Run the commands against a virtual Hc12 behind a pseudo-terminal.
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use clap::Parser;
+use embedded_hal::digital::OutputPin;
+use hc12_at::hc12::baud_rate::ReconfigureBaudRate;
+use hc12_at::settings::parameter::{
+    baudrate::BaudRate, channel::Channel, mode::Mode, parameters::Parameters,
+    transmission_power::TransmissionPower,
+};
+use hc12_at::sim::VirtualHc12;
+use hc12_cli::{run, Cli, CliError};
+use num_traits::ToPrimitive;
+use serialport::{SerialPort, TTYPort};
+
+/// Forwards bytes between the master side of a pseudo-terminal and a virtual Hc12.
+/// The baud rate of the slave side is handed on to the host side of the module.
+struct Bridge {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Bridge {
+    fn new(mut master: TTYPort, slave: TTYPort, module: &VirtualHc12) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut serial = module.serial();
+        let stopped = stop.clone();
+        master.set_timeout(Duration::from_millis(1)).unwrap();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+            while !stopped.load(Ordering::Relaxed) {
+                let count = match master.read(&mut buffer) {
+                    Ok(count) => count,
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => 0,
+                    // The command finished and closed the slave side
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return,
+                    Err(e) => panic!("{}", e),
+                };
+                if count > 0 {
+                    let rate = slave.baud_rate().unwrap();
+                    let rate = BaudRate::try_from(rate as i32).unwrap();
+                    serial.set_baud_rate(rate).unwrap();
+                    embedded_io::Write::write_all(&mut serial, &buffer[..count]).unwrap();
+                }
+                while embedded_io::ReadReady::read_ready(&mut serial).unwrap() {
+                    let count = embedded_io::Read::read(&mut serial, &mut buffer).unwrap();
+                    master.write_all(&buffer[..count]).unwrap();
+                }
+            }
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        // Let bytes which are still in the terminal reach the module
+        thread::sleep(Duration::from_millis(20));
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Run the command line against the module, with SET at the given level
+fn hc12(module: &VirtualHc12, set_low: bool, args: &[&str]) -> Result<String, CliError> {
+    let mut set_pin = module.set_pin();
+    if set_low {
+        set_pin.set_low().unwrap();
+    } else {
+        set_pin.set_high().unwrap();
+    }
+    let (master, slave) = TTYPort::pair().unwrap();
+    let _bridge = Bridge::new(master, slave.try_clone_native().unwrap(), module);
+    let cli = Cli::try_parse_from(std::iter::once("hc12").chain(args.iter().copied())).unwrap();
+    let mut slave = slave;
+    slave
+        .set_baud_rate(cli.port_baud.to_u32().unwrap())
+        .unwrap();
+    let mut out = Vec::new();
+    run(&cli, slave, &mut io::empty(), &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn info() {
+    let module = VirtualHc12::default();
+    let out = hc12(&module, true, &["info"]).unwrap();
+    assert!(out.contains("firmware:      www.hc01.com HC-12 V2.6\n"));
+    assert!(out.contains("channel:       1 (433.4 MHz)\n"));
+    assert!(out.contains("mode:          FU3\n"));
+
+    let out = hc12(&module, true, &["--json", "info"]).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json["firmware"]["model"], "HC-12");
+    assert_eq!(json["parameters"]["baud_rate"], 9600);
+    assert_eq!(json["parameters"]["power_dbm"], 20);
+    assert_eq!(json["parameters"]["serial_format"], "8N1");
+}
+
+#[test]
+fn set_parameters() {
+    let module = VirtualHc12::default();
+    hc12(
+        &module,
+        true,
+        &["set", "--channel", "21", "--power", "4", "--mode", "fu1"],
+    )
+    .unwrap();
+    let expected = Parameters {
+        channel: Channel::new(21).unwrap(),
+        power: TransmissionPower::Four,
+        mode: Mode::Fu1,
+        ..Parameters::default()
+    };
+    assert_eq!(module.parameters(), expected);
+
+    // The serial port follows the module to the new baud rate
+    let out = hc12(&module, true, &["--json", "set", "--baud", "19200"]).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json["parameters"]["baud_rate"], 19200);
+    assert_eq!(module.parameters().baud_rate, BaudRate::Bps19200);
+}
+
+#[test]
+fn set_mode_with_baud_rate() {
+    let module = VirtualHc12::default();
+    // FU4 needs 1200 bps, so the baud rate changes first
+    hc12(&module, true, &["set", "--mode", "FU4", "--baud", "1200"]).unwrap();
+    assert_eq!(module.parameters().mode, Mode::Fu4);
+    assert_eq!(module.parameters().baud_rate, BaudRate::Bps1200);
+
+    let err = hc12(
+        &module,
+        true,
+        &[
+            "--port-baud",
+            "1200",
+            "set",
+            "--mode",
+            "2",
+            "--baud",
+            "115200",
+        ],
+    )
+    .unwrap_err();
+    assert!(matches!(err, CliError::Hc12(_)));
+    assert_eq!(module.parameters().mode, Mode::Fu4);
+}
+
+#[test]
+fn reset_and_sleep() {
+    let module = VirtualHc12::new(Parameters {
+        channel: Channel::new(100).unwrap(),
+        ..Parameters::default()
+    });
+    let out = hc12(&module, true, &["reset"]).unwrap();
+    assert_eq!(out, "reset to factory defaults\n");
+    assert_eq!(module.parameters(), Parameters::default());
+
+    let out = hc12(&module, true, &["--json", "sleep"]).unwrap();
+    assert_eq!(out, "{\"status\":\"sleeping once SET is released\"}\n");
+    module.set_pin().set_high().unwrap();
+    assert!(module.is_sleeping());
+}
+
+#[test]
+fn probe() {
+    let module = VirtualHc12::new(Parameters {
+        baud_rate: BaudRate::Bps2400,
+        ..Parameters::default()
+    });
+    let out = hc12(&module, true, &["--json", "probe"]).unwrap();
+    assert_eq!(out, "{\"baud_rate\":2400}\n");
+}
+
+#[test]
+fn send_and_listen() {
+    let module = VirtualHc12::default();
+    let out = hc12(&module, false, &["send", "hello"]).unwrap();
+    assert_eq!(out, "sent 5 bytes\n");
+    assert_eq!(module.take_transmitted(), b"hello");
+
+    assert!(module.receive(b"ping"));
+    let out = hc12(
+        &module,
+        false,
+        &["--json", "listen", "--duration-ms", "100"],
+    )
+    .unwrap();
+    assert_eq!(out, "{\"data\":\"ping\",\"hex\":\"70696e67\"}\n");
+}
+
+#[test]
+fn invalid_arguments() {
+    assert!(Cli::try_parse_from(["hc12", "set", "--channel", "200"]).is_err());
+    assert!(Cli::try_parse_from(["hc12", "set", "--baud", "9601"]).is_err());
+    assert!(Cli::try_parse_from(["hc12", "set", "--mode", "FU5"]).is_err());
+    assert!(Cli::try_parse_from(["hc12", "set", "--power", "0"]).is_err());
+}
+
+#[test]
+fn set_pin_on_control_line() {
+    let module = VirtualHc12::default();
+    // Pseudo-terminals have no modem control lines
+    let err = hc12(&module, true, &["--set-pin", "rts", "info"]).unwrap_err();
+    match err {
+        CliError::Hc12(message) => assert!(message.starts_with("Pin(")),
+        e => panic!("{}", e),
+    }
+    assert!(Cli::try_parse_from(["hc12", "--set-pin", "cts", "info"]).is_err());
+}
@@ -2,7 +2,6 @@
 #![no_main]
 
 use embedded_hal::digital::v2::OutputPin;
-use hc12_at::hc12::Hc12;
 use panic_halt as _;
 
 use gd32vf103xx_hal::serial::Serial;
@@ -72,13 +71,10 @@ fn main() -> ! {
         rx: serial.1,
     };
 
-    let mut hc12 = Hc12::new(ms, set_pin, delay);
+    let mut hc12 = hc12_at::compat::new(ms, set_pin, delay).debugless_unwrap();
 
     loop {
-        hc12.write_buffer(b"Hello World").debugless_unwrap();
-        hc12.write(b'\r').debugless_unwrap();
-        hc12.write(b'\n').debugless_unwrap();
-        hc12.flush().debugless_unwrap();
+        hc12.write_buffer(b"Hello World\r\n").debugless_unwrap();
         led.set_low().debugless_unwrap();
         delay.delay_ms(250);
         led.set_high().debugless_unwrap();
@@ -9,21 +9,26 @@ fn main() -> Result<(), ()> {
 
     let set_pin = Gpio::new().unwrap().get(18).unwrap().into_output();
 
-    let hc12 = hc12_at::hc12::Hc12::new(uart, set_pin, linux_embedded_hal::Delay);
-
-    let mut hc12 = match hc12.into_configuration_mode() {
+    let hc12 = hc12_at::compat::new(uart, set_pin, linux_embedded_hal::Delay).unwrap();
+
+    let (mut hc12, params) = match hc12.with_configuration(|hc12| {
+        hc12.is_ok()?;
+
+        let version = hc12.get_firmware_version()?;
+        println!(
+            "{} V{}.{}",
+            std::str::from_utf8(version.model()).unwrap(),
+            version.major,
+            version.minor
+        );
+        println!("{:?}", version.capabilities());
+
+        hc12.get_parameters()
+    }) {
         Ok(r) => r,
         Err(_) => panic!(),
     };
 
-    assert!(hc12.is_ok());
-
-    let mut buffer = [0u8; 64];
-    let result = hc12.get_version(&mut buffer);
-    println!("{:?}", std::str::from_utf8(&result).unwrap());
-
-    let params = hc12.get_parameters().unwrap();
-
     println!("{:#?}", params);
 
     println!("{:?}", params.get_air_baud_rate());
@@ -33,11 +38,6 @@ fn main() -> Result<(), ()> {
         params.get_air_baud_rate().get_wireless_sensitivity_dbm()
     );
 
-    let mut hc12 = match hc12.into_normal_mode() {
-        Ok(r) => r,
-        Err(_) => panic!(),
-    };
-
     loop {
         hc12.write_buffer(b"hello hc12\r\n").unwrap();
         thread::sleep(Duration::from_millis(500));